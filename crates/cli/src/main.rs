@@ -117,6 +117,14 @@ fn main() -> eyre::Result<()> {
         &target_dir,
     )?;
 
+    if build_mode == BuildMode::Release {
+        copy_app_bundle(
+            &flutter_build_dir,
+            &flion_build_dir,
+            &target_dir.join("data"),
+        )?;
+    }
+
     compile_plugins_shim(&flion_build_dir.join("plugins"), &target_dir)?;
 
     process_plugins(&flutter_program, flutter_project_dir, &target_dir)?;
@@ -467,6 +475,23 @@ fn copy_native_libraries(
     Ok(())
 }
 
+/// Copies the flutter assets and AOT library next to the executable, since release builds don't
+/// read their paths from the environment.
+fn copy_app_bundle(
+    flutter_build_dir: &Path,
+    flion_build_dir: &Path,
+    out_dir: &Path,
+) -> eyre::Result<()> {
+    copy_dir_if_newer(
+        &flutter_build_dir.join("flutter_assets"),
+        &out_dir.join("flutter_assets"),
+    )?;
+
+    copy_if_newer(&flion_build_dir.join("app.so"), &out_dir.join("app.so"))?;
+
+    Ok(())
+}
+
 fn compile_plugins_shim(build_dir: &Path, out_dir: &Path) -> eyre::Result<()> {
     fs::create_dir_all(build_dir)?;
 
@@ -642,6 +667,23 @@ fn process_plugins(
     Ok(())
 }
 
+fn copy_dir_if_newer(src: &Path, dst: &Path) -> eyre::Result<()> {
+    fs::create_dir_all(dst)?;
+
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let src = entry.path();
+        let dst = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_if_newer(&src, &dst)?;
+        } else {
+            copy_if_newer(&src, &dst)?;
+        }
+    }
+
+    Ok(())
+}
+
 fn copy_if_newer(src: &Path, dst: &Path) -> eyre::Result<()> {
     if dst.exists() {
         let src_metadata = src.metadata()?;
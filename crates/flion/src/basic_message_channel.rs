@@ -0,0 +1,244 @@
+use std::ffi::CString;
+use std::io::Cursor;
+use std::str::FromStr;
+
+use crate::codec::{self, CodecExtension, DecodeLimits, Decoder, EncodableValue, NoExtension};
+use crate::engine::{BinaryMessageHandler, BinaryMessageReply, BinaryMessenger};
+use crate::error::Error;
+
+/// Converts between messages and the bytes sent over a platform channel. This corresponds to
+/// `MessageCodec` in Dart.
+pub trait MessageCodec: Clone {
+    type Message<'a>;
+
+    fn encode_message(&self, message: &Self::Message<'_>) -> crate::Result<Vec<u8>>;
+
+    fn decode_message<'a>(&self, bytes: &'a [u8]) -> crate::Result<Self::Message<'a>>;
+}
+
+/// Passes bytes through unchanged, like `BinaryCodec` in Dart.
+#[derive(Clone, Copy, Default)]
+pub struct BinaryCodec;
+
+impl MessageCodec for BinaryCodec {
+    type Message<'a> = &'a [u8];
+
+    fn encode_message(&self, message: &&[u8]) -> crate::Result<Vec<u8>> {
+        Ok(message.to_vec())
+    }
+
+    fn decode_message<'a>(&self, bytes: &'a [u8]) -> crate::Result<&'a [u8]> {
+        Ok(bytes)
+    }
+}
+
+/// UTF-8 encoded strings, like `StringCodec` in Dart.
+#[derive(Clone, Copy, Default)]
+pub struct StringCodec;
+
+impl MessageCodec for StringCodec {
+    type Message<'a> = &'a str;
+
+    fn encode_message(&self, message: &&str) -> crate::Result<Vec<u8>> {
+        Ok(message.as_bytes().to_vec())
+    }
+
+    fn decode_message<'a>(&self, bytes: &'a [u8]) -> crate::Result<&'a str> {
+        std::str::from_utf8(bytes).map_err(Error::codec)
+    }
+}
+
+/// JSON values, like `JSONMessageCodec` in Dart.
+#[derive(Clone, Copy, Default)]
+pub struct JsonMessageCodec;
+
+impl MessageCodec for JsonMessageCodec {
+    type Message<'a> = serde_json::Value;
+
+    fn encode_message(&self, message: &serde_json::Value) -> crate::Result<Vec<u8>> {
+        Ok(serde_json::to_vec(message)?)
+    }
+
+    fn decode_message(&self, bytes: &[u8]) -> crate::Result<serde_json::Value> {
+        // Null messages and replies are sent as empty.
+        if bytes.is_empty() {
+            return Ok(serde_json::Value::Null);
+        }
+
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// Values supported by the standard codec, like `StandardMessageCodec` in Dart.
+#[derive(Clone, Copy)]
+pub struct StandardMessageCodec {
+    extension: &'static dyn CodecExtension,
+    limits: DecodeLimits,
+}
+
+impl StandardMessageCodec {
+    pub fn with_extension(extension: &'static dyn CodecExtension) -> StandardMessageCodec {
+        StandardMessageCodec {
+            extension,
+            limits: DecodeLimits::default(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: DecodeLimits) -> StandardMessageCodec {
+        self.limits = limits;
+        self
+    }
+}
+
+impl Default for StandardMessageCodec {
+    fn default() -> Self {
+        StandardMessageCodec::with_extension(&NoExtension)
+    }
+}
+
+impl MessageCodec for StandardMessageCodec {
+    type Message<'a> = EncodableValue<'a>;
+
+    fn encode_message(&self, message: &EncodableValue) -> crate::Result<Vec<u8>> {
+        let mut bytes = vec![];
+        codec::write_value_with(&mut Cursor::new(&mut bytes), message, self.extension)
+            .map_err(Error::codec)?;
+        Ok(bytes)
+    }
+
+    fn decode_message<'a>(&self, bytes: &'a [u8]) -> crate::Result<EncodableValue<'a>> {
+        // Null messages and replies are sent as empty.
+        if bytes.is_empty() {
+            return Ok(EncodableValue::Null);
+        }
+
+        let mut decoder = Decoder::new(self.extension).with_limits(self.limits);
+        decoder
+            .read_value(&mut Cursor::new(bytes))
+            .map_err(Error::codec)
+    }
+}
+
+/// Sends and receives messages on a `BasicMessageChannel` on the Dart side.
+pub struct BasicMessageChannel<C> {
+    messenger: BinaryMessenger,
+    name: CString,
+    codec: C,
+}
+
+impl<C: MessageCodec + 'static> BasicMessageChannel<C> {
+    pub fn new(messenger: BinaryMessenger, name: &str, codec: C) -> crate::Result<Self> {
+        Ok(BasicMessageChannel {
+            messenger,
+            name: CString::from_str(name).map_err(Error::InvalidChannelName)?,
+            codec,
+        })
+    }
+
+    pub fn send(&self, message: &C::Message<'_>) -> crate::Result<()> {
+        let bytes = self.codec.encode_message(message)?;
+        self.messenger.send_platform_message(&self.name, &bytes)
+    }
+
+    pub fn send_with_reply<F>(
+        &self,
+        message: &C::Message<'_>,
+        reply_handler: F,
+    ) -> crate::Result<()>
+    where
+        F: FnOnce(crate::Result<C::Message<'_>>) + 'static,
+    {
+        let bytes = self.codec.encode_message(message)?;
+        let codec = self.codec.clone();
+        self.messenger
+            .send_platform_message_with_reply(&self.name, &bytes, move |reply| {
+                reply_handler(codec.decode_message(reply))
+            })
+    }
+
+    /// Sets the handler for messages sent from Dart on this channel, replacing any previous
+    /// handler.
+    pub fn set_message_handler(
+        &self,
+        handler: impl BasicMessageHandler<C> + 'static,
+    ) -> crate::Result<()> {
+        let handler = ChannelHandler {
+            codec: self.codec.clone(),
+            handler,
+        };
+
+        self.messenger
+            .set_message_handler(self.name.to_string_lossy(), handler)?;
+
+        Ok(())
+    }
+
+    /// Removes the handler for this channel, if there is one.
+    pub fn remove_message_handler(&self) -> crate::Result<()> {
+        self.messenger
+            .remove_message_handler(&self.name.to_string_lossy())?;
+        Ok(())
+    }
+}
+
+pub trait BasicMessageHandler<C: MessageCodec> {
+    fn handle(&self, message: C::Message<'_>, reply: BasicMessageReply<C>);
+}
+
+impl<C, F> BasicMessageHandler<C> for F
+where
+    C: MessageCodec,
+    F: Fn(C::Message<'_>, BasicMessageReply<C>),
+{
+    fn handle(&self, message: C::Message<'_>, reply: BasicMessageReply<C>) {
+        self(message, reply)
+    }
+}
+
+/// Decodes messages for a [BasicMessageHandler] that is registered as a platform message handler.
+struct ChannelHandler<C, H> {
+    codec: C,
+    handler: H,
+}
+
+impl<C: MessageCodec, H: BasicMessageHandler<C>> BinaryMessageHandler for ChannelHandler<C, H> {
+    fn handle(&self, message: &[u8], reply: BinaryMessageReply) {
+        let message = match self.codec.decode_message(message) {
+            Ok(message) => message,
+            Err(e) => {
+                tracing::error!("failed to decode message: {e}");
+                reply.not_implemented();
+                return;
+            }
+        };
+
+        let reply = BasicMessageReply {
+            reply,
+            codec: self.codec.clone(),
+        };
+
+        self.handler.handle(message, reply);
+    }
+}
+
+pub struct BasicMessageReply<C> {
+    reply: BinaryMessageReply,
+    codec: C,
+}
+
+impl<C: MessageCodec> BasicMessageReply<C> {
+    pub fn send(self, message: &C::Message<'_>) {
+        match self.codec.encode_message(message) {
+            Ok(bytes) => self.reply.send(&bytes),
+            Err(e) => {
+                tracing::error!("failed to encode reply: {e}");
+                self.reply.not_implemented();
+            }
+        }
+    }
+
+    /// Replies with an empty message, which is received as `null` in Dart.
+    pub fn send_empty(self) {
+        self.reply.send(&[]);
+    }
+}
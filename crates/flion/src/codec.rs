@@ -1,10 +1,25 @@
+mod de;
+mod error;
+mod extract;
+mod ser;
+
+use std::any::Any;
+use std::borrow::Cow;
 use std::collections::BTreeMap;
+use std::fmt::{self, Debug};
 use std::io::{self, Seek, Write};
+use std::sync::Arc;
+use std::{mem, ptr, slice};
 
 use byteorder::{NativeEndian, ReadBytesExt, WriteBytesExt};
 
-type ReadCursor<'a> = std::io::Cursor<&'a [u8]>;
-type WriteCursor<'a> = std::io::Cursor<&'a mut Vec<u8>>;
+pub use self::de::{from_slice, from_value};
+pub use self::error::Error;
+pub use self::extract::{FromEncodable, IntoEncodable};
+pub use self::ser::{to_value, to_vec};
+
+pub type ReadCursor<'a> = std::io::Cursor<&'a [u8]>;
+pub type WriteCursor<'a> = std::io::Cursor<&'a mut Vec<u8>>;
 
 #[repr(u8)]
 enum EncodedType {
@@ -25,10 +40,10 @@ enum EncodedType {
     Float32List,
 }
 
-impl From<u8> for EncodedType {
-    fn from(value: u8) -> Self {
+impl EncodedType {
+    fn from_tag(tag: u8) -> Option<EncodedType> {
         use EncodedType::*;
-        match value {
+        Some(match tag {
             0 => Null,
             1 => True,
             2 => False,
@@ -44,25 +59,124 @@ impl From<u8> for EncodedType {
             12 => List,
             13 => Map,
             14 => Float32List,
-            _ => panic!("invalid EncodedType: {value}"),
-        }
+            _ => return None,
+        })
+    }
+}
+
+/// Extends the standard codec with application-specific types, equivalent to subclassing
+/// `StandardMessageCodec` in Dart. Custom types should use type tags of 128 and above.
+///
+/// Extensions must be `Sync`, since they are shared with event sinks on background threads.
+pub trait CodecExtension: Sync {
+    /// Reads a value whose type tag is not one of the standard types. The tag itself has already
+    /// been consumed from `cursor`.
+    ///
+    /// Nested values should be read with [Decoder::read_value], so that the decoder's limits
+    /// still apply to them.
+    fn read_value<'a>(
+        &self,
+        tag: u8,
+        cursor: &mut ReadCursor<'a>,
+        decoder: &mut Decoder,
+    ) -> io::Result<EncodableValue<'a>>;
+
+    /// Writes a custom value, including its type tag.
+    ///
+    /// Nested values can be written with [write_value_with], passing `self` as the extension.
+    fn write_value(&self, w: &mut WriteCursor, value: &CustomValue) -> io::Result<()>;
+}
+
+/// The default extension, which only supports the standard types.
+pub struct NoExtension;
+
+impl CodecExtension for NoExtension {
+    fn read_value<'a>(
+        &self,
+        tag: u8,
+        _cursor: &mut ReadCursor<'a>,
+        _decoder: &mut Decoder,
+    ) -> io::Result<EncodableValue<'a>> {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid EncodedType: {tag}"),
+        ))
+    }
+
+    fn write_value(&self, _w: &mut WriteCursor, value: &CustomValue) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("no codec extension to write {value:?}"),
+        ))
+    }
+}
+
+/// A value of an application-specific type, see [CodecExtension].
+///
+/// Custom values are compared by identity, since the codec knows nothing about their contents.
+#[derive(Clone)]
+pub struct CustomValue(Arc<dyn Any + Send + Sync>);
+
+impl CustomValue {
+    pub fn new<T: Any + Send + Sync>(value: T) -> CustomValue {
+        CustomValue(Arc::new(value))
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.downcast_ref()
+    }
+
+    pub fn is<T: Any>(&self) -> bool {
+        self.0.is::<T>()
+    }
+
+    fn addr(&self) -> *const () {
+        Arc::as_ptr(&self.0).cast()
+    }
+}
+
+impl Debug for CustomValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CustomValue").field(&self.addr()).finish()
+    }
+}
+
+impl PartialEq for CustomValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.addr() == other.addr()
+    }
+}
+
+impl Eq for CustomValue {}
+
+impl PartialOrd for CustomValue {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CustomValue {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.addr().cmp(&other.addr())
     }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(transparent)]
 pub struct Float64(pub f64);
 
 impl Eq for Float64 {}
 
 impl PartialOrd for Float64 {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.0.partial_cmp(&other.0)
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for Float64 {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap()
+        // Values decoded from a message may be NaN, so this needs to be a total ordering.
+        self.0.total_cmp(&other.0)
     }
 }
 
@@ -72,17 +186,50 @@ impl From<f64> for Float64 {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(transparent)]
+pub struct Float32(pub f32);
+
+impl Eq for Float32 {}
+
+impl PartialOrd for Float32 {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Float32 {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl From<f32> for Float32 {
+    fn from(value: f32) -> Self {
+        Float32(value)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum EncodableValue<'a> {
     Null,
     Bool(bool),
     I32(i32),
     I64(i64),
+    /// An integer too large to fit in an `i64`, as a hexadecimal string.
+    LargeInt(Cow<'a, str>),
     F64(Float64),
-    Str(&'a str),
+    Str(Cow<'a, str>),
     List(Vec<EncodableValue<'a>>),
-    U8List(Vec<u8>),
+    // Typed lists borrow from the message they were decoded from when its contents are suitably
+    // aligned in memory, and are copied otherwise.
+    U8List(Cow<'a, [u8]>),
+    I32List(Cow<'a, [i32]>),
+    I64List(Cow<'a, [i64]>),
+    F32List(Cow<'a, [Float32]>),
+    F64List(Cow<'a, [Float64]>),
     Map(BTreeMap<EncodableValue<'a>, EncodableValue<'a>>),
+    Custom(CustomValue),
 }
 
 impl<'a> EncodableValue<'a> {
@@ -110,7 +257,15 @@ impl<'a> EncodableValue<'a> {
         }
     }
 
-    pub fn as_string(&self) -> Option<&'a str> {
+    pub fn as_f64(&self) -> Option<f64> {
+        if let Self::F64(v) = self {
+            Some(v.0)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_string(&self) -> Option<&str> {
         if let Self::Str(v) = self {
             Some(v)
         } else {
@@ -118,6 +273,62 @@ impl<'a> EncodableValue<'a> {
         }
     }
 
+    pub fn as_u8_list(&self) -> Option<&[u8]> {
+        if let Self::U8List(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_i32_list(&self) -> Option<&[i32]> {
+        if let Self::I32List(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_i64_list(&self) -> Option<&[i64]> {
+        if let Self::I64List(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_f32_list(&self) -> Option<&[Float32]> {
+        if let Self::F32List(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_f64_list(&self) -> Option<&[Float64]> {
+        if let Self::F64List(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[EncodableValue<'a>]> {
+        if let Self::List(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    pub fn into_list(self) -> Option<Vec<EncodableValue<'a>>> {
+        if let Self::List(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
     pub fn as_map(&self) -> Option<&BTreeMap<EncodableValue<'a>, EncodableValue<'a>>> {
         if let Self::Map(v) = self {
             Some(v)
@@ -133,9 +344,111 @@ impl<'a> EncodableValue<'a> {
             None
         }
     }
+
+    pub fn as_custom<T: Any>(&self) -> Option<&T> {
+        if let Self::Custom(v) = self {
+            v.downcast_ref()
+        } else {
+            None
+        }
+    }
+
+    /// Converts this value into one that does not borrow from the message it was decoded from,
+    /// so that it can be held on to after the handler has returned.
+    pub fn into_owned(self) -> EncodableValue<'static> {
+        match self {
+            EncodableValue::Null => EncodableValue::Null,
+            EncodableValue::Bool(v) => EncodableValue::Bool(v),
+            EncodableValue::I32(v) => EncodableValue::I32(v),
+            EncodableValue::I64(v) => EncodableValue::I64(v),
+            EncodableValue::LargeInt(v) => EncodableValue::LargeInt(Cow::Owned(v.into_owned())),
+            EncodableValue::F64(v) => EncodableValue::F64(v),
+            EncodableValue::Str(v) => EncodableValue::Str(Cow::Owned(v.into_owned())),
+            EncodableValue::List(v) => {
+                EncodableValue::List(v.into_iter().map(EncodableValue::into_owned).collect())
+            }
+            EncodableValue::U8List(v) => EncodableValue::U8List(Cow::Owned(v.into_owned())),
+            EncodableValue::I32List(v) => EncodableValue::I32List(Cow::Owned(v.into_owned())),
+            EncodableValue::I64List(v) => EncodableValue::I64List(Cow::Owned(v.into_owned())),
+            EncodableValue::F32List(v) => EncodableValue::F32List(Cow::Owned(v.into_owned())),
+            EncodableValue::F64List(v) => EncodableValue::F64List(Cow::Owned(v.into_owned())),
+            EncodableValue::Map(v) => EncodableValue::Map(
+                v.into_iter()
+                    .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                    .collect(),
+            ),
+            EncodableValue::Custom(v) => EncodableValue::Custom(v),
+        }
+    }
 }
 
-fn read_size(cursor: &mut ReadCursor) -> io::Result<u32> {
+impl From<bool> for EncodableValue<'_> {
+    fn from(value: bool) -> Self {
+        EncodableValue::Bool(value)
+    }
+}
+
+impl From<i32> for EncodableValue<'_> {
+    fn from(value: i32) -> Self {
+        EncodableValue::I32(value)
+    }
+}
+
+impl From<i64> for EncodableValue<'_> {
+    fn from(value: i64) -> Self {
+        EncodableValue::I64(value)
+    }
+}
+
+impl From<f64> for EncodableValue<'_> {
+    fn from(value: f64) -> Self {
+        EncodableValue::F64(Float64(value))
+    }
+}
+
+impl<'a> From<&'a str> for EncodableValue<'a> {
+    fn from(value: &'a str) -> Self {
+        EncodableValue::Str(Cow::Borrowed(value))
+    }
+}
+
+impl From<String> for EncodableValue<'_> {
+    fn from(value: String) -> Self {
+        EncodableValue::Str(Cow::Owned(value))
+    }
+}
+
+impl<'a> From<Cow<'a, str>> for EncodableValue<'a> {
+    fn from(value: Cow<'a, str>) -> Self {
+        EncodableValue::Str(value)
+    }
+}
+
+impl<'a> From<&'a [u8]> for EncodableValue<'a> {
+    fn from(value: &'a [u8]) -> Self {
+        EncodableValue::U8List(Cow::Borrowed(value))
+    }
+}
+
+impl From<Vec<u8>> for EncodableValue<'_> {
+    fn from(value: Vec<u8>) -> Self {
+        EncodableValue::U8List(Cow::Owned(value))
+    }
+}
+
+impl<'a> From<Vec<EncodableValue<'a>>> for EncodableValue<'a> {
+    fn from(value: Vec<EncodableValue<'a>>) -> Self {
+        EncodableValue::List(value)
+    }
+}
+
+impl<'a> From<BTreeMap<EncodableValue<'a>, EncodableValue<'a>>> for EncodableValue<'a> {
+    fn from(value: BTreeMap<EncodableValue<'a>, EncodableValue<'a>>) -> Self {
+        EncodableValue::Map(value)
+    }
+}
+
+pub fn read_size(cursor: &mut ReadCursor) -> io::Result<u32> {
     match cursor.read_u8()? {
         254 => cursor.read_u16::<NativeEndian>().map(u32::from),
         255 => cursor.read_u32::<NativeEndian>(),
@@ -143,7 +456,7 @@ fn read_size(cursor: &mut ReadCursor) -> io::Result<u32> {
     }
 }
 
-fn write_size(w: &mut impl io::Write, value: u32) -> io::Result<()> {
+pub fn write_size(w: &mut impl io::Write, value: u32) -> io::Result<()> {
     if value < 254 {
         w.write_u8(value as u8)?;
     } else if value <= u16::MAX.into() {
@@ -156,10 +469,20 @@ fn write_size(w: &mut impl io::Write, value: u32) -> io::Result<()> {
     Ok(())
 }
 
+fn read_bytes<'a>(cursor: &mut ReadCursor<'a>, size: usize) -> io::Result<&'a [u8]> {
+    let start = cursor.position() as usize;
+    let buf = cursor
+        .get_ref()
+        .get(start..)
+        .and_then(|remaining| remaining.get(..size))
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+    cursor.set_position((start + size) as u64);
+    Ok(buf)
+}
+
 fn read_string<'a>(cursor: &mut ReadCursor<'a>) -> io::Result<&'a str> {
     let size = read_size(cursor)?;
-    let buf = &cursor.get_ref()[cursor.position() as usize..][..size as usize];
-    cursor.set_position(cursor.position() + size as u64);
+    let buf = read_bytes(cursor, size as usize)?;
     std::str::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
 }
 
@@ -169,30 +492,93 @@ fn write_string(w: &mut WriteCursor, value: &str) -> io::Result<()> {
     Ok(())
 }
 
-fn write_list(w: &mut WriteCursor, values: &[EncodableValue]) -> io::Result<()> {
-    write_size(w, values.len() as u32)?;
-    for v in values {
-        write_value(w, v)?;
+fn read_list<'a>(
+    cursor: &mut ReadCursor<'a>,
+    decoder: &mut Decoder,
+) -> io::Result<Vec<EncodableValue<'a>>> {
+    let size = decoder.check_collection_len(read_size(cursor)?)?;
+    let mut list = Vec::new();
+    for _ in 0..size {
+        list.push(decoder.read_value(cursor)?);
     }
-    Ok(())
+    Ok(list)
 }
 
-fn write_u8_list(w: &mut WriteCursor, values: &[u8]) -> io::Result<()> {
+fn write_list(
+    w: &mut WriteCursor,
+    values: &[EncodableValue],
+    extension: &dyn CodecExtension,
+) -> io::Result<()> {
     write_size(w, values.len() as u32)?;
     for v in values {
-        w.write_u8(*v)?;
+        write_value_with(w, v, extension)?;
     }
     Ok(())
 }
 
+/// Element types of typed lists.
+///
+/// # Safety
+///
+/// Implementors must be plain old data, valid for any bit pattern, so that they can be read
+/// directly from and written directly to the message buffer.
+unsafe trait TypedListElement: Copy {}
+
+unsafe impl TypedListElement for u8 {}
+unsafe impl TypedListElement for i32 {}
+unsafe impl TypedListElement for i64 {}
+unsafe impl TypedListElement for Float32 {}
+unsafe impl TypedListElement for Float64 {}
+
+fn read_typed_list<'a, T: TypedListElement>(
+    cursor: &mut ReadCursor<'a>,
+    decoder: &mut Decoder,
+) -> io::Result<Cow<'a, [T]>> {
+    let size = decoder.check_collection_len(read_size(cursor)?)?;
+    let element_size = mem::size_of::<T>();
+    skip_alignment(cursor, element_size);
+    let byte_size = size
+        .checked_mul(element_size)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "list size overflow"))?;
+    let buf = read_bytes(cursor, byte_size)?;
+
+    // The codec only aligns elements relative to the start of the message, so the buffer itself
+    // may still be misaligned in memory.
+    if buf.as_ptr().align_offset(mem::align_of::<T>()) == 0 {
+        // SAFETY: The pointer is aligned for T, the buffer holds exactly `size` elements, and T is
+        // valid for any bit pattern.
+        Ok(Cow::Borrowed(unsafe {
+            slice::from_raw_parts(buf.as_ptr().cast::<T>(), size)
+        }))
+    } else {
+        decoder.allocate(byte_size)?;
+        Ok(Cow::Owned(
+            buf.chunks_exact(element_size)
+                // SAFETY: Each chunk holds exactly one T, which is valid for any bit pattern.
+                .map(|chunk| unsafe { ptr::read_unaligned(chunk.as_ptr().cast::<T>()) })
+                .collect(),
+        ))
+    }
+}
+
+fn write_typed_list<T: TypedListElement>(w: &mut WriteCursor, values: &[T]) -> io::Result<()> {
+    write_size(w, values.len() as u32)?;
+    align_to(w, mem::size_of::<T>())?;
+    // SAFETY: T is plain old data, so its memory can be viewed as bytes.
+    let bytes =
+        unsafe { slice::from_raw_parts(values.as_ptr().cast::<u8>(), mem::size_of_val(values)) };
+    w.write_all(bytes)
+}
+
 fn read_map<'a>(
     cursor: &mut ReadCursor<'a>,
+    decoder: &mut Decoder,
 ) -> io::Result<BTreeMap<EncodableValue<'a>, EncodableValue<'a>>> {
-    let size = read_size(cursor)?;
+    let size = decoder.check_collection_len(read_size(cursor)?)?;
     let mut map = BTreeMap::new();
     for _ in 0..size {
-        let key = read_value(cursor)?;
-        let value = read_value(cursor)?;
+        let key = decoder.read_value(cursor)?;
+        let value = decoder.read_value(cursor)?;
         map.insert(key, value);
     }
     Ok(map)
@@ -201,39 +587,173 @@ fn read_map<'a>(
 fn write_map(
     w: &mut WriteCursor,
     value: &BTreeMap<EncodableValue, EncodableValue>,
+    extension: &dyn CodecExtension,
 ) -> io::Result<()> {
     write_size(w, value.len() as u32)?;
     for (k, v) in value {
-        write_value(w, k)?;
-        write_value(w, v)?;
+        write_value_with(w, k, extension)?;
+        write_value_with(w, v, extension)?;
     }
     Ok(())
 }
 
 pub fn read_value<'a>(cursor: &mut ReadCursor<'a>) -> io::Result<EncodableValue<'a>> {
-    let encoded_type = EncodedType::from(cursor.read_u8()?);
-    match encoded_type {
-        EncodedType::Null => Ok(EncodableValue::Null),
-        EncodedType::True => Ok(EncodableValue::Bool(true)),
-        EncodedType::False => Ok(EncodableValue::Bool(false)),
-        EncodedType::Int32 => Ok(EncodableValue::I32(cursor.read_i32::<NativeEndian>()?)),
-        EncodedType::Int64 => Ok(EncodableValue::I64(cursor.read_i64::<NativeEndian>()?)),
-        EncodedType::LargeInt => todo!(),
-        EncodedType::Float64 => Ok(EncodableValue::F64(Float64(
-            cursor.read_f64::<NativeEndian>()?,
-        ))),
-        EncodedType::String => Ok(EncodableValue::Str(read_string(cursor)?)),
-        EncodedType::UInt8List => todo!(),
-        EncodedType::Int32List => todo!(),
-        EncodedType::Int64List => todo!(),
-        EncodedType::Float64List => todo!(),
-        EncodedType::List => todo!(),
-        EncodedType::Map => Ok(EncodableValue::Map(read_map(cursor)?)),
-        EncodedType::Float32List => todo!(),
+    Decoder::default().read_value(cursor)
+}
+
+/// Reads a value, using `extension` to read any non-standard types.
+pub fn read_value_with<'a>(
+    cursor: &mut ReadCursor<'a>,
+    extension: &dyn CodecExtension,
+) -> io::Result<EncodableValue<'a>> {
+    Decoder::new(extension).read_value(cursor)
+}
+
+/// Limits on the resources used to decode a message, which may come from untrusted input.
+#[derive(Clone, Copy, Debug)]
+pub struct DecodeLimits {
+    /// The maximum nesting depth of values. A value that is not inside any list or map has a
+    /// depth of 1.
+    pub max_depth: usize,
+    /// The maximum number of elements in a list, typed list or map.
+    pub max_collection_len: usize,
+    /// The maximum number of bytes allocated for decoded values. Strings and typed lists that
+    /// borrow from the message do not count towards this.
+    pub max_allocation: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        DecodeLimits {
+            max_depth: 128,
+            max_collection_len: 1 << 24,
+            max_allocation: 256 << 20,
+        }
+    }
+}
+
+/// Reads values from a message, while enforcing [DecodeLimits].
+pub struct Decoder<'e> {
+    extension: &'e dyn CodecExtension,
+    limits: DecodeLimits,
+    depth: usize,
+    allocated: usize,
+}
+
+impl Default for Decoder<'_> {
+    fn default() -> Self {
+        Decoder::new(&NoExtension)
     }
 }
 
+impl<'e> Decoder<'e> {
+    pub fn new(extension: &'e dyn CodecExtension) -> Decoder<'e> {
+        Decoder {
+            extension,
+            limits: DecodeLimits::default(),
+            depth: 0,
+            allocated: 0,
+        }
+    }
+
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn read_value<'a>(
+        &mut self,
+        cursor: &mut ReadCursor<'a>,
+    ) -> io::Result<EncodableValue<'a>> {
+        if self.depth >= self.limits.max_depth {
+            return Err(limit_exceeded(format!(
+                "nesting depth exceeds limit of {}",
+                self.limits.max_depth
+            )));
+        }
+
+        self.allocate(mem::size_of::<EncodableValue>())?;
+
+        self.depth += 1;
+        let value = self.read_value_of_type(cursor);
+        self.depth -= 1;
+
+        value
+    }
+
+    fn read_value_of_type<'a>(
+        &mut self,
+        cursor: &mut ReadCursor<'a>,
+    ) -> io::Result<EncodableValue<'a>> {
+        let tag = cursor.read_u8()?;
+        let Some(encoded_type) = EncodedType::from_tag(tag) else {
+            let extension = self.extension;
+            return extension.read_value(tag, cursor, self);
+        };
+
+        match encoded_type {
+            EncodedType::Null => Ok(EncodableValue::Null),
+            EncodedType::True => Ok(EncodableValue::Bool(true)),
+            EncodedType::False => Ok(EncodableValue::Bool(false)),
+            EncodedType::Int32 => Ok(EncodableValue::I32(cursor.read_i32::<NativeEndian>()?)),
+            EncodedType::Int64 => Ok(EncodableValue::I64(cursor.read_i64::<NativeEndian>()?)),
+            EncodedType::LargeInt => Ok(EncodableValue::LargeInt(Cow::Borrowed(read_string(
+                cursor,
+            )?))),
+            EncodedType::Float64 => {
+                skip_alignment(cursor, 8);
+                Ok(EncodableValue::F64(Float64(
+                    cursor.read_f64::<NativeEndian>()?,
+                )))
+            }
+            EncodedType::String => Ok(EncodableValue::Str(Cow::Borrowed(read_string(cursor)?))),
+            EncodedType::UInt8List => Ok(EncodableValue::U8List(read_typed_list(cursor, self)?)),
+            EncodedType::Int32List => Ok(EncodableValue::I32List(read_typed_list(cursor, self)?)),
+            EncodedType::Int64List => Ok(EncodableValue::I64List(read_typed_list(cursor, self)?)),
+            EncodedType::Float64List => Ok(EncodableValue::F64List(read_typed_list(cursor, self)?)),
+            EncodedType::List => Ok(EncodableValue::List(read_list(cursor, self)?)),
+            EncodedType::Map => Ok(EncodableValue::Map(read_map(cursor, self)?)),
+            EncodedType::Float32List => Ok(EncodableValue::F32List(read_typed_list(cursor, self)?)),
+        }
+    }
+
+    fn check_collection_len(&self, len: u32) -> io::Result<usize> {
+        let len = len as usize;
+        if len > self.limits.max_collection_len {
+            return Err(limit_exceeded(format!(
+                "collection length {len} exceeds limit of {}",
+                self.limits.max_collection_len
+            )));
+        }
+        Ok(len)
+    }
+
+    fn allocate(&mut self, size: usize) -> io::Result<()> {
+        self.allocated = self.allocated.saturating_add(size);
+        if self.allocated > self.limits.max_allocation {
+            return Err(limit_exceeded(format!(
+                "decoded values exceed allocation limit of {} bytes",
+                self.limits.max_allocation
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn limit_exceeded(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
 pub fn write_value(w: &mut WriteCursor, value: &EncodableValue) -> io::Result<()> {
+    write_value_with(w, value, &NoExtension)
+}
+
+/// Writes a value, using `extension` to write any [EncodableValue::Custom] values.
+pub fn write_value_with(
+    w: &mut WriteCursor,
+    value: &EncodableValue,
+    extension: &dyn CodecExtension,
+) -> io::Result<()> {
     match value {
         EncodableValue::Null => {
             w.write_u8(EncodedType::Null as u8)?;
@@ -253,6 +773,10 @@ pub fn write_value(w: &mut WriteCursor, value: &EncodableValue) -> io::Result<()
             w.write_u8(EncodedType::Int64 as u8)?;
             w.write_i64::<NativeEndian>(*v)?;
         }
+        EncodableValue::LargeInt(v) => {
+            w.write_u8(EncodedType::LargeInt as u8)?;
+            write_string(w, v)?;
+        }
         EncodableValue::F64(v) => {
             w.write_u8(EncodedType::Float64 as u8)?;
             align_to(w, 8)?;
@@ -264,15 +788,34 @@ pub fn write_value(w: &mut WriteCursor, value: &EncodableValue) -> io::Result<()
         }
         EncodableValue::List(v) => {
             w.write_u8(EncodedType::List as u8)?;
-            write_list(w, v)?;
+            write_list(w, v, extension)?;
         }
         EncodableValue::U8List(v) => {
             w.write_u8(EncodedType::UInt8List as u8)?;
-            write_u8_list(w, v)?;
+            write_typed_list(w, v)?;
+        }
+        EncodableValue::I32List(v) => {
+            w.write_u8(EncodedType::Int32List as u8)?;
+            write_typed_list(w, v)?;
+        }
+        EncodableValue::I64List(v) => {
+            w.write_u8(EncodedType::Int64List as u8)?;
+            write_typed_list(w, v)?;
+        }
+        EncodableValue::F32List(v) => {
+            w.write_u8(EncodedType::Float32List as u8)?;
+            write_typed_list(w, v)?;
+        }
+        EncodableValue::F64List(v) => {
+            w.write_u8(EncodedType::Float64List as u8)?;
+            write_typed_list(w, v)?;
         }
         EncodableValue::Map(v) => {
             w.write_u8(EncodedType::Map as u8)?;
-            write_map(w, v)?;
+            write_map(w, v, extension)?;
+        }
+        EncodableValue::Custom(v) => {
+            extension.write_value(w, v)?;
         }
     }
 
@@ -290,3 +833,10 @@ fn align_to(w: &mut WriteCursor, align: usize) -> io::Result<()> {
     }
     Ok(())
 }
+
+fn skip_alignment(cursor: &mut ReadCursor, align: usize) {
+    let m = cursor.position() as usize % align;
+    if m != 0 {
+        cursor.set_position(cursor.position() + (align - m) as u64);
+    }
+}
@@ -0,0 +1,330 @@
+use std::borrow::Cow;
+use std::collections::btree_map;
+use std::io::Cursor;
+use std::vec;
+
+use serde::de::{self, Deserialize, DeserializeSeed, Visitor};
+use serde::forward_to_deserialize_any;
+
+use super::error::PathSegment;
+use super::{EncodableValue, Error};
+
+/// Deserializes a `T` from an [EncodableValue].
+///
+/// Strings that borrow from the original message can be deserialized into `&str` fields without
+/// copying.
+pub fn from_value<'de, T: Deserialize<'de>>(value: EncodableValue<'de>) -> Result<T, Error> {
+    T::deserialize(ValueDeserializer(value))
+}
+
+/// Deserializes a `T` from standard codec bytes. It is an error for there to be any bytes left
+/// over after the value.
+pub fn from_slice<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T, Error> {
+    let mut cursor = Cursor::new(bytes);
+    let value = super::read_value(&mut cursor)?;
+
+    let remaining = bytes.len() - cursor.position() as usize;
+    if remaining != 0 {
+        return Err(Error::new(format!(
+            "{remaining} trailing bytes after value"
+        )));
+    }
+
+    from_value(value)
+}
+
+struct ValueDeserializer<'de>(EncodableValue<'de>);
+
+fn visit_str<'de, V: Visitor<'de>>(value: Cow<'de, str>, visitor: V) -> Result<V::Value, Error> {
+    match value {
+        Cow::Borrowed(v) => visitor.visit_borrowed_str(v),
+        Cow::Owned(v) => visitor.visit_string(v),
+    }
+}
+
+fn visit_list<'de, V: Visitor<'de>>(
+    values: Vec<EncodableValue<'de>>,
+    visitor: V,
+) -> Result<V::Value, Error> {
+    let mut seq = SeqDeserializer {
+        iter: values.into_iter(),
+        index: 0,
+    };
+    let value = visitor.visit_seq(&mut seq)?;
+    if seq.iter.len() != 0 {
+        return Err(de::Error::invalid_length(
+            seq.index + seq.iter.len(),
+            &"fewer elements in list",
+        ));
+    }
+    Ok(value)
+}
+
+fn into_list(value: EncodableValue) -> Option<Vec<EncodableValue>> {
+    Some(match value {
+        EncodableValue::List(v) => v,
+        EncodableValue::U8List(v) => v.iter().map(|&v| EncodableValue::I32(v.into())).collect(),
+        EncodableValue::I32List(v) => v.iter().map(|&v| EncodableValue::I32(v)).collect(),
+        EncodableValue::I64List(v) => v.iter().map(|&v| EncodableValue::I64(v)).collect(),
+        EncodableValue::F32List(v) => v.iter().map(|v| EncodableValue::from(v.0 as f64)).collect(),
+        EncodableValue::F64List(v) => v.iter().map(|&v| EncodableValue::F64(v)).collect(),
+        _ => return None,
+    })
+}
+
+fn unexpected<'a>(value: &'a EncodableValue) -> de::Unexpected<'a> {
+    match value {
+        EncodableValue::Null => de::Unexpected::Unit,
+        EncodableValue::Bool(v) => de::Unexpected::Bool(*v),
+        EncodableValue::I32(v) => de::Unexpected::Signed((*v).into()),
+        EncodableValue::I64(v) => de::Unexpected::Signed(*v),
+        EncodableValue::LargeInt(_) => de::Unexpected::Other("large integer"),
+        EncodableValue::F64(v) => de::Unexpected::Float(v.0),
+        EncodableValue::Str(v) => de::Unexpected::Str(v),
+        EncodableValue::List(_) => de::Unexpected::Seq,
+        EncodableValue::U8List(v) => de::Unexpected::Bytes(v),
+        EncodableValue::I32List(_)
+        | EncodableValue::I64List(_)
+        | EncodableValue::F32List(_)
+        | EncodableValue::F64List(_) => de::Unexpected::Other("typed list"),
+        EncodableValue::Map(_) => de::Unexpected::Map,
+        EncodableValue::Custom(_) => de::Unexpected::Other("custom value"),
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            EncodableValue::Null => visitor.visit_unit(),
+            EncodableValue::Bool(v) => visitor.visit_bool(v),
+            EncodableValue::I32(v) => visitor.visit_i32(v),
+            EncodableValue::I64(v) => visitor.visit_i64(v),
+            EncodableValue::LargeInt(v) => visit_str(v, visitor),
+            EncodableValue::F64(v) => visitor.visit_f64(v.0),
+            EncodableValue::Str(v) => visit_str(v, visitor),
+            EncodableValue::Map(v) => {
+                let mut map = MapDeserializer {
+                    iter: v.into_iter(),
+                    value: None,
+                };
+                let value = visitor.visit_map(&mut map)?;
+                if map.iter.len() != 0 {
+                    return Err(de::Error::invalid_length(
+                        map.iter.len(),
+                        &"fewer entries in map",
+                    ));
+                }
+                Ok(value)
+            }
+            value @ EncodableValue::Custom(_) => {
+                Err(de::Error::invalid_type(unexpected(&value), &visitor))
+            }
+            value => visit_list(into_list(value).unwrap(), visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            EncodableValue::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            EncodableValue::U8List(Cow::Borrowed(v)) => visitor.visit_borrowed_bytes(v),
+            EncodableValue::U8List(Cow::Owned(v)) => visitor.visit_byte_buf(v),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.0 {
+            EncodableValue::Str(_) => visitor.visit_enum(EnumDeserializer {
+                variant: self.0,
+                value: None,
+            }),
+            EncodableValue::Map(v) if v.len() == 1 => {
+                let (variant, value) = v.into_iter().next().unwrap();
+                visitor.visit_enum(EnumDeserializer {
+                    variant,
+                    value: Some(value),
+                })
+            }
+            value => Err(de::Error::invalid_type(
+                unexpected(&value),
+                &"a string or a map with a single key",
+            )),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+struct SeqDeserializer<'de> {
+    iter: vec::IntoIter<EncodableValue<'de>>,
+    index: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqDeserializer<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        let Some(value) = self.iter.next() else {
+            return Ok(None);
+        };
+        let index = self.index;
+        self.index += 1;
+        seed.deserialize(ValueDeserializer(value))
+            .map(Some)
+            .map_err(|e| e.in_index(index))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapDeserializer<'de> {
+    iter: btree_map::IntoIter<EncodableValue<'de>, EncodableValue<'de>>,
+    value: Option<(PathSegment, EncodableValue<'de>)>,
+}
+
+impl<'de> de::MapAccess<'de> for MapDeserializer<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some((key, value)) = self.iter.next() else {
+            return Ok(None);
+        };
+        // Remember which key the value belongs to, so that errors can report it.
+        self.value = Some((PathSegment::key(&key), value));
+        seed.deserialize(ValueDeserializer(key)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (segment, value) = self
+            .value
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        seed.deserialize(ValueDeserializer(value))
+            .map_err(|e| e.within(segment))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct EnumDeserializer<'de> {
+    variant: EncodableValue<'de>,
+    value: Option<EncodableValue<'de>>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer<'de> {
+    type Error = Error;
+    type Variant = VariantDeserializer<'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Error> {
+        let segment = PathSegment::key(&self.variant);
+        let variant = seed.deserialize(ValueDeserializer(self.variant))?;
+        Ok((
+            variant,
+            VariantDeserializer {
+                segment,
+                value: self.value,
+            },
+        ))
+    }
+}
+
+struct VariantDeserializer<'de> {
+    segment: PathSegment,
+    value: Option<EncodableValue<'de>>,
+}
+
+impl<'de> de::VariantAccess<'de> for VariantDeserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.value {
+            None | Some(EncodableValue::Null) => Ok(()),
+            Some(value) => Err(de::Error::invalid_type(unexpected(&value), &"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        let value = self.value.unwrap_or(EncodableValue::Null);
+        seed.deserialize(ValueDeserializer(value))
+            .map_err(|e| e.within(self.segment))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Some(value @ EncodableValue::List(_)) => {
+                de::Deserializer::deserialize_any(ValueDeserializer(value), visitor)
+                    .map_err(|e| e.within(self.segment))
+            }
+            value => Err(de::Error::invalid_type(
+                value
+                    .as_ref()
+                    .map_or(de::Unexpected::UnitVariant, unexpected),
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.value {
+            Some(value @ EncodableValue::Map(_)) => {
+                de::Deserializer::deserialize_any(ValueDeserializer(value), visitor)
+                    .map_err(|e| e.within(self.segment))
+            }
+            value => Err(de::Error::invalid_type(
+                value
+                    .as_ref()
+                    .map_or(de::Unexpected::UnitVariant, unexpected),
+                &"struct variant",
+            )),
+        }
+    }
+}
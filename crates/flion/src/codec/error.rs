@@ -0,0 +1,106 @@
+use std::fmt::{self, Display};
+use std::io;
+
+use super::EncodableValue;
+
+/// An error produced while converting between standard codec values and Rust types.
+#[derive(Debug)]
+pub struct Error {
+    path: Vec<PathSegment>,
+    message: String,
+}
+
+#[derive(Debug)]
+pub(super) enum PathSegment {
+    Field(String),
+    Index(usize),
+}
+
+impl PathSegment {
+    pub(super) fn key(key: &EncodableValue) -> PathSegment {
+        match key {
+            EncodableValue::Str(name) => PathSegment::Field(name.as_ref().to_owned()),
+            key => PathSegment::Field(format!("{key:?}")),
+        }
+    }
+}
+
+impl Error {
+    pub(super) fn new(message: impl Display) -> Error {
+        Error {
+            path: vec![],
+            message: message.to_string(),
+        }
+    }
+
+    pub(super) fn invalid_type(expected: &str, value: &EncodableValue) -> Error {
+        Error::new(format!("expected {expected}, found {}", value.type_name()))
+    }
+
+    /// The message describing what went wrong, without the path.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The path to the value that caused the error, e.g. `args.items[2].name`.
+    pub fn path(&self) -> String {
+        let mut path = String::new();
+        for segment in &self.path {
+            match segment {
+                PathSegment::Field(name) => {
+                    if !path.is_empty() {
+                        path.push('.');
+                    }
+                    path.push_str(name);
+                }
+                PathSegment::Index(index) => {
+                    path.push_str(&format!("[{index}]"));
+                }
+            }
+        }
+        path
+    }
+
+    pub(super) fn within(mut self, segment: PathSegment) -> Error {
+        self.path.insert(0, segment);
+        self
+    }
+
+    pub(super) fn in_field(self, name: &str) -> Error {
+        self.within(PathSegment::Field(name.to_owned()))
+    }
+
+    pub(super) fn in_index(self, index: usize) -> Error {
+        self.within(PathSegment::Index(index))
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path(), self.message)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl serde::de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::new(msg)
+    }
+}
+
+impl serde::ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::new(msg)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Error::new(value)
+    }
+}
@@ -0,0 +1,333 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+use super::error::PathSegment;
+use super::{EncodableValue, Error, Float64};
+
+impl<'a> EncodableValue<'a> {
+    /// Looks up a field of a map by its string key.
+    pub fn get(&self, key: &str) -> Option<&EncodableValue<'a>> {
+        // Map keys are compared as values, so the key needs the same lifetime as the map. An owned
+        // key satisfies any lifetime.
+        self.as_map()?
+            .get(&EncodableValue::Str(Cow::Owned(key.to_owned())))
+    }
+
+    /// Reads an integer of either width, or a float with no fractional part. Dart sends integers
+    /// as whichever of `I32` or `I64` fits the value, so handlers should generally use this over
+    /// matching on a specific variant.
+    pub fn to_i64(&self) -> Option<i64> {
+        match self {
+            EncodableValue::I32(v) => Some((*v).into()),
+            EncodableValue::I64(v) => Some(*v),
+            // i64::MAX isn't representable as an f64, so the upper bound must be exclusive.
+            EncodableValue::F64(Float64(v))
+                if v.fract() == 0.0 && *v >= i64::MIN as f64 && *v < i64::MAX as f64 =>
+            {
+                Some(*v as i64)
+            }
+            _ => None,
+        }
+    }
+
+    /// Like [EncodableValue::to_i64], but fails if the value doesn't fit in an `i32`.
+    pub fn to_i32(&self) -> Option<i32> {
+        self.to_i64()?.try_into().ok()
+    }
+
+    /// Reads a float, or an integer of either width.
+    pub fn to_f64(&self) -> Option<f64> {
+        match self {
+            EncodableValue::I32(v) => Some((*v).into()),
+            EncodableValue::I64(v) => Some(*v as f64),
+            EncodableValue::F64(v) => Some(v.0),
+            _ => None,
+        }
+    }
+
+    /// Converts this value into `T`.
+    pub fn extract<'v, T: FromEncodable<'v>>(&'v self) -> Result<T, Error> {
+        T::from_encodable(self)
+    }
+
+    /// Extracts a field of a map, failing if the field is missing or has the wrong type.
+    pub fn field<'v, T: FromEncodable<'v>>(&'v self, name: &str) -> Result<T, Error> {
+        match self.optional_field(name)? {
+            Some(value) => Ok(value),
+            None => Err(Error::new(format!("missing field `{name}`"))),
+        }
+    }
+
+    /// Extracts a field of a map, returning `None` if the field is missing or null.
+    pub fn optional_field<'v, T: FromEncodable<'v>>(
+        &'v self,
+        name: &str,
+    ) -> Result<Option<T>, Error> {
+        if !matches!(self, EncodableValue::Map(_)) {
+            return Err(Error::invalid_type("map", self));
+        }
+
+        match self.get(name) {
+            None | Some(EncodableValue::Null) => Ok(None),
+            Some(value) => T::from_encodable(value)
+                .map(Some)
+                .map_err(|e| e.in_field(name)),
+        }
+    }
+
+    /// A short description of the type of this value, for use in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            EncodableValue::Null => "null",
+            EncodableValue::Bool(_) => "bool",
+            EncodableValue::I32(_) => "i32",
+            EncodableValue::I64(_) => "i64",
+            EncodableValue::LargeInt(_) => "large int",
+            EncodableValue::F64(_) => "f64",
+            EncodableValue::Str(_) => "string",
+            EncodableValue::List(_) => "list",
+            EncodableValue::U8List(_) => "u8 list",
+            EncodableValue::I32List(_) => "i32 list",
+            EncodableValue::I64List(_) => "i64 list",
+            EncodableValue::F32List(_) => "f32 list",
+            EncodableValue::F64List(_) => "f64 list",
+            EncodableValue::Map(_) => "map",
+            EncodableValue::Custom(_) => "custom value",
+        }
+    }
+}
+
+/// Types that can be extracted from an [EncodableValue], borrowing from it where possible.
+pub trait FromEncodable<'v>: Sized {
+    fn from_encodable(value: &'v EncodableValue) -> Result<Self, Error>;
+}
+
+impl<'v> FromEncodable<'v> for &'v EncodableValue<'v> {
+    fn from_encodable(value: &'v EncodableValue) -> Result<Self, Error> {
+        Ok(value)
+    }
+}
+
+impl FromEncodable<'_> for EncodableValue<'static> {
+    fn from_encodable(value: &EncodableValue) -> Result<Self, Error> {
+        Ok(value.clone().into_owned())
+    }
+}
+
+impl FromEncodable<'_> for bool {
+    fn from_encodable(value: &EncodableValue) -> Result<Self, Error> {
+        value
+            .as_bool()
+            .ok_or_else(|| Error::invalid_type("bool", value))
+    }
+}
+
+macro_rules! impl_from_encodable_int {
+    ($($t:ty),*) => {
+        $(
+            impl FromEncodable<'_> for $t {
+                fn from_encodable(value: &EncodableValue) -> Result<Self, Error> {
+                    let v = value
+                        .to_i64()
+                        .ok_or_else(|| Error::invalid_type("integer", value))?;
+                    <$t>::try_from(v).map_err(|_| {
+                        Error::new(format!("{v} is out of range for {}", stringify!($t)))
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_from_encodable_int!(i32, i64, u32, u64, usize);
+
+impl FromEncodable<'_> for f64 {
+    fn from_encodable(value: &EncodableValue) -> Result<Self, Error> {
+        value
+            .to_f64()
+            .ok_or_else(|| Error::invalid_type("number", value))
+    }
+}
+
+impl FromEncodable<'_> for f32 {
+    fn from_encodable(value: &EncodableValue) -> Result<Self, Error> {
+        f64::from_encodable(value).map(|v| v as f32)
+    }
+}
+
+impl<'v> FromEncodable<'v> for &'v str {
+    fn from_encodable(value: &'v EncodableValue) -> Result<Self, Error> {
+        value
+            .as_string()
+            .ok_or_else(|| Error::invalid_type("string", value))
+    }
+}
+
+impl FromEncodable<'_> for String {
+    fn from_encodable(value: &EncodableValue) -> Result<Self, Error> {
+        <&str>::from_encodable(value).map(str::to_owned)
+    }
+}
+
+impl<'v> FromEncodable<'v> for &'v [u8] {
+    fn from_encodable(value: &'v EncodableValue) -> Result<Self, Error> {
+        value
+            .as_u8_list()
+            .ok_or_else(|| Error::invalid_type("u8 list", value))
+    }
+}
+
+impl FromEncodable<'_> for Vec<u8> {
+    fn from_encodable(value: &EncodableValue) -> Result<Self, Error> {
+        <&[u8]>::from_encodable(value).map(<[u8]>::to_vec)
+    }
+}
+
+impl<'v, T: FromEncodable<'v>> FromEncodable<'v> for Option<T> {
+    fn from_encodable(value: &'v EncodableValue) -> Result<Self, Error> {
+        match value {
+            EncodableValue::Null => Ok(None),
+            value => T::from_encodable(value).map(Some),
+        }
+    }
+}
+
+impl<'v, T: FromEncodable<'v>> FromEncodable<'v> for Vec<T> {
+    fn from_encodable(value: &'v EncodableValue) -> Result<Self, Error> {
+        let list = value
+            .as_list()
+            .ok_or_else(|| Error::invalid_type("list", value))?;
+
+        list.iter()
+            .enumerate()
+            .map(|(i, v)| T::from_encodable(v).map_err(|e| e.in_index(i)))
+            .collect()
+    }
+}
+
+fn map_entry<'v, K, V>(k: &'v EncodableValue, v: &'v EncodableValue) -> Result<(K, V), Error>
+where
+    K: FromEncodable<'v>,
+    V: FromEncodable<'v>,
+{
+    let key = K::from_encodable(k).map_err(|e| e.within(PathSegment::key(k)))?;
+    let value = V::from_encodable(v).map_err(|e| e.within(PathSegment::key(k)))?;
+    Ok((key, value))
+}
+
+impl<'v, K, V> FromEncodable<'v> for BTreeMap<K, V>
+where
+    K: FromEncodable<'v> + Ord,
+    V: FromEncodable<'v>,
+{
+    fn from_encodable(value: &'v EncodableValue) -> Result<Self, Error> {
+        let map = value
+            .as_map()
+            .ok_or_else(|| Error::invalid_type("map", value))?;
+
+        map.iter().map(|(k, v)| map_entry(k, v)).collect()
+    }
+}
+
+impl<'v, K, V> FromEncodable<'v> for HashMap<K, V>
+where
+    K: FromEncodable<'v> + Eq + Hash,
+    V: FromEncodable<'v>,
+{
+    fn from_encodable(value: &'v EncodableValue) -> Result<Self, Error> {
+        let map = value
+            .as_map()
+            .ok_or_else(|| Error::invalid_type("map", value))?;
+
+        map.iter().map(|(k, v)| map_entry(k, v)).collect()
+    }
+}
+
+/// Types that can be converted into an [EncodableValue].
+pub trait IntoEncodable<'a> {
+    fn into_encodable(self) -> EncodableValue<'a>;
+}
+
+impl<'a> IntoEncodable<'a> for EncodableValue<'a> {
+    fn into_encodable(self) -> EncodableValue<'a> {
+        self
+    }
+}
+
+impl IntoEncodable<'_> for () {
+    fn into_encodable(self) -> EncodableValue<'static> {
+        EncodableValue::Null
+    }
+}
+
+macro_rules! impl_into_encodable_via_from {
+    ($($t:ty),*) => {
+        $(
+            impl<'a> IntoEncodable<'a> for $t {
+                fn into_encodable(self) -> EncodableValue<'a> {
+                    EncodableValue::from(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_into_encodable_via_from!(
+    bool,
+    i32,
+    i64,
+    f64,
+    &'a str,
+    String,
+    Cow<'a, str>,
+    &'a [u8],
+    Vec<u8>
+);
+
+impl IntoEncodable<'_> for u32 {
+    fn into_encodable(self) -> EncodableValue<'static> {
+        EncodableValue::I64(self.into())
+    }
+}
+
+impl IntoEncodable<'_> for f32 {
+    fn into_encodable(self) -> EncodableValue<'static> {
+        EncodableValue::F64(Float64(self.into()))
+    }
+}
+
+impl<'a, T: IntoEncodable<'a>> IntoEncodable<'a> for Option<T> {
+    fn into_encodable(self) -> EncodableValue<'a> {
+        match self {
+            Some(value) => value.into_encodable(),
+            None => EncodableValue::Null,
+        }
+    }
+}
+
+impl<'a, T: IntoEncodable<'a>> IntoEncodable<'a> for Vec<T> {
+    fn into_encodable(self) -> EncodableValue<'a> {
+        EncodableValue::List(self.into_iter().map(T::into_encodable).collect())
+    }
+}
+
+impl<'a, K: IntoEncodable<'a>, V: IntoEncodable<'a>> IntoEncodable<'a> for BTreeMap<K, V> {
+    fn into_encodable(self) -> EncodableValue<'a> {
+        EncodableValue::Map(
+            self.into_iter()
+                .map(|(k, v)| (k.into_encodable(), v.into_encodable()))
+                .collect(),
+        )
+    }
+}
+
+impl<'a, K: IntoEncodable<'a>, V: IntoEncodable<'a>> IntoEncodable<'a> for HashMap<K, V> {
+    fn into_encodable(self) -> EncodableValue<'a> {
+        EncodableValue::Map(
+            self.into_iter()
+                .map(|(k, v)| (k.into_encodable(), v.into_encodable()))
+                .collect(),
+        )
+    }
+}
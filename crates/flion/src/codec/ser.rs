@@ -0,0 +1,384 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+use serde::ser::{self, Serialize};
+
+use super::error::PathSegment;
+use super::{EncodableValue, Error, Float64};
+
+/// Converts `value` into an [EncodableValue].
+///
+/// Integers are encoded as `I32` where they fit, and `I64` otherwise, matching the Dart
+/// implementation of the codec. Structs are encoded as maps with string keys, and enums follow
+/// the same externally tagged representation as `serde_json`.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<EncodableValue<'static>, Error> {
+    value.serialize(ValueSerializer)
+}
+
+/// Serializes `value` to standard codec bytes.
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+    let value = to_value(value)?;
+    let mut bytes = vec![];
+    super::write_value(&mut Cursor::new(&mut bytes), &value)?;
+    Ok(bytes)
+}
+
+struct ValueSerializer;
+
+fn integer(value: i64) -> EncodableValue<'static> {
+    match i32::try_from(value) {
+        Ok(value) => EncodableValue::I32(value),
+        Err(_) => EncodableValue::I64(value),
+    }
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = EncodableValue<'static>;
+    type Error = Error;
+
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeTupleVariant;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeStruct;
+    type SerializeStructVariant = SerializeStructVariant;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Error> {
+        Ok(EncodableValue::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Error> {
+        Ok(integer(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Error> {
+        Ok(integer(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Error> {
+        Ok(integer(v.into()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Error> {
+        Ok(integer(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Error> {
+        Ok(integer(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Error> {
+        Ok(integer(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Error> {
+        Ok(integer(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Error> {
+        match i64::try_from(v) {
+            Ok(v) => Ok(integer(v)),
+            Err(_) => Err(ser::Error::custom(format!(
+                "integer {v} is out of range for the standard codec"
+            ))),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Error> {
+        Ok(EncodableValue::F64(Float64(v.into())))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Error> {
+        Ok(EncodableValue::F64(Float64(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Error> {
+        Ok(EncodableValue::Str(Cow::Owned(v.to_string())))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Error> {
+        Ok(EncodableValue::Str(Cow::Owned(v.to_owned())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Error> {
+        Ok(EncodableValue::from(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Error> {
+        Ok(EncodableValue::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Error> {
+        Ok(EncodableValue::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Error> {
+        Ok(EncodableValue::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Error> {
+        Ok(EncodableValue::from(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        let value = value.serialize(self).map_err(|e| e.in_field(variant))?;
+        Ok(EncodableValue::Map(BTreeMap::from([(
+            EncodableValue::from(variant),
+            value,
+        )])))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Ok(SerializeList {
+            values: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Ok(SerializeTupleVariant {
+            variant,
+            list: SerializeList {
+                values: Vec::with_capacity(len),
+            },
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Ok(SerializeMap {
+            map: BTreeMap::new(),
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Ok(SerializeStruct {
+            map: BTreeMap::new(),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Ok(SerializeStructVariant {
+            variant,
+            fields: SerializeStruct {
+                map: BTreeMap::new(),
+            },
+        })
+    }
+}
+
+struct SerializeList {
+    values: Vec<EncodableValue<'static>>,
+}
+
+impl SerializeList {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let index = self.values.len();
+        let value = value
+            .serialize(ValueSerializer)
+            .map_err(|e| e.in_index(index))?;
+        self.values.push(value);
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = EncodableValue<'static>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(EncodableValue::List(self.values))
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = EncodableValue<'static>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(EncodableValue::List(self.values))
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = EncodableValue<'static>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(EncodableValue::List(self.values))
+    }
+}
+
+struct SerializeTupleVariant {
+    variant: &'static str,
+    list: SerializeList,
+}
+
+impl ser::SerializeTupleVariant for SerializeTupleVariant {
+    type Ok = EncodableValue<'static>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.list.push(value).map_err(|e| e.in_field(self.variant))
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(EncodableValue::Map(BTreeMap::from([(
+            EncodableValue::from(self.variant),
+            EncodableValue::List(self.list.values),
+        )])))
+    }
+}
+
+struct SerializeMap {
+    map: BTreeMap<EncodableValue<'static>, EncodableValue<'static>>,
+    next_key: Option<EncodableValue<'static>>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = EncodableValue<'static>;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.next_key = Some(key.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .next_key
+            .take()
+            .expect("serialize_value called before serialize_key");
+        let value = value
+            .serialize(ValueSerializer)
+            .map_err(|e| e.within(PathSegment::key(&key)))?;
+        self.map.insert(key, value);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(EncodableValue::Map(self.map))
+    }
+}
+
+struct SerializeStruct {
+    map: BTreeMap<EncodableValue<'static>, EncodableValue<'static>>,
+}
+
+impl SerializeStruct {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        let value = value
+            .serialize(ValueSerializer)
+            .map_err(|e| e.in_field(key))?;
+        self.map.insert(EncodableValue::from(key), value);
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for SerializeStruct {
+    type Ok = EncodableValue<'static>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(EncodableValue::Map(self.map))
+    }
+}
+
+struct SerializeStructVariant {
+    variant: &'static str,
+    fields: SerializeStruct,
+}
+
+impl ser::SerializeStructVariant for SerializeStructVariant {
+    type Ok = EncodableValue<'static>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.fields
+            .insert(key, value)
+            .map_err(|e| e.in_field(self.variant))
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(EncodableValue::Map(BTreeMap::from([(
+            EncodableValue::from(self.variant),
+            EncodableValue::Map(self.fields.map),
+        )])))
+    }
+}
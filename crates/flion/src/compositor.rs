@@ -28,6 +28,7 @@ use windows::Win32::System::Threading::{CreateEventW, WaitForSingleObject, INFIN
 use windows_numerics::Matrix3x2;
 
 use crate::egl::EglDevice;
+use crate::panic_guard::catch_panic;
 use crate::platform_views::{PlatformViewUpdateArgs, PlatformViews};
 use crate::views::ViewManager;
 
@@ -161,39 +162,45 @@ impl FlutterCompositor {
             user_data: *mut c_void,
             gl_state_changed: *mut bool,
         ) -> bool {
-            let layer = unsafe {
-                user_data
-                    .cast::<CompositorFlutterLayer>()
-                    .as_mut()
-                    .expect("layer is not null")
-            };
+            catch_panic("make_surface_current", || {
+                let layer = unsafe {
+                    user_data
+                        .cast::<CompositorFlutterLayer>()
+                        .as_mut()
+                        .expect("layer is not null")
+                };
 
-            if let Err(e) = layer.egl.make_surface_current(layer.egl_surface) {
-                tracing::error!("{e:?}");
-                return false;
-            };
+                if let Err(e) = layer.egl.make_surface_current(layer.egl_surface) {
+                    tracing::error!("{e:?}");
+                    return false;
+                };
 
-            unsafe {
-                *gl_state_changed = false;
-            }
+                unsafe {
+                    *gl_state_changed = false;
+                }
 
-            true
+                true
+            })
+            .unwrap_or(false)
         }
 
         extern "C" fn clear_current_surface(user_data: *mut c_void, _: *mut bool) -> bool {
-            let layer = unsafe {
-                user_data
-                    .cast::<CompositorFlutterLayer>()
-                    .as_mut()
-                    .expect("layer is not null")
-            };
+            catch_panic("clear_current_surface", || {
+                let layer = unsafe {
+                    user_data
+                        .cast::<CompositorFlutterLayer>()
+                        .as_mut()
+                        .expect("layer is not null")
+                };
 
-            if let Err(e) = layer.egl.clear_current() {
-                tracing::error!("{e:?}");
-                return false;
-            }
+                if let Err(e) = layer.egl.clear_current() {
+                    tracing::error!("{e:?}");
+                    return false;
+                }
 
-            true
+                true
+            })
+            .unwrap_or(false)
         }
 
         const GL_BGRA8_EXT: u32 = 0x93A1;
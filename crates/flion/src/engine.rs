@@ -1,29 +1,36 @@
-use std::collections::BTreeMap;
-use std::ffi::{c_char, c_void, CStr, CString};
+use std::cell::{Ref, RefCell};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::ffi::{c_char, c_void, CStr, CString, NulError};
 use std::marker::PhantomData;
+use std::ops::Bound;
 use std::path::Path;
+use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use std::{mem, ptr};
 
 use bitflags::bitflags;
-use eyre::{bail, Context};
+use eyre::Context;
 use flutter_embedder::{
     FlutterBackingStore, FlutterBackingStoreConfig, FlutterCustomTaskRunners, FlutterEngineAOTData,
     FlutterEngineAOTDataSource,
     FlutterEngineAOTDataSourceType_kFlutterEngineAOTDataSourceTypeElfPath,
     FlutterEngineAOTDataSource__bindgen_ty_1, FlutterEngineCreateAOTData,
-    FlutterEngineGetCurrentTime, FlutterEngineInitialize, FlutterEngineResult_kSuccess,
-    FlutterEngineRunInitialized, FlutterEngineRunTask, FlutterEngineRunsAOTCompiledDartCode,
-    FlutterEngineScheduleFrame, FlutterEngineSendKeyEvent, FlutterEngineSendPlatformMessage,
-    FlutterEngineSendPlatformMessageResponse, FlutterEngineSendPointerEvent,
-    FlutterEngineSendWindowMetricsEvent, FlutterEngineShutdown, FlutterKeyEvent,
+    FlutterEngineDispatchSemanticsAction, FlutterEngineGetCurrentTime, FlutterEngineInitialize,
+    FlutterEngineResult_kSuccess, FlutterEngineRunInitialized, FlutterEngineRunTask,
+    FlutterEngineRunsAOTCompiledDartCode, FlutterEngineScheduleFrame, FlutterEngineSendKeyEvent,
+    FlutterEngineSendPlatformMessage, FlutterEngineSendPlatformMessageResponse,
+    FlutterEngineSendPointerEvent, FlutterEngineSendWindowMetricsEvent, FlutterEngineShutdown,
+    FlutterEngineUpdateLocales, FlutterEngineUpdateSemanticsEnabled, FlutterKeyEvent,
     FlutterKeyEventDeviceType_kFlutterKeyEventDeviceTypeKeyboard,
     FlutterKeyEventType_kFlutterKeyEventTypeDown, FlutterKeyEventType_kFlutterKeyEventTypeRepeat,
-    FlutterKeyEventType_kFlutterKeyEventTypeUp, FlutterLayer, FlutterOpenGLRendererConfig,
-    FlutterPlatformMessage, FlutterPlatformMessageCreateResponseHandle,
-    FlutterPlatformMessageReleaseResponseHandle, FlutterPlatformMessageResponseHandle,
-    FlutterPointerDeviceKind, FlutterPointerDeviceKind_kFlutterPointerDeviceKindMouse,
+    FlutterKeyEventType_kFlutterKeyEventTypeUp, FlutterLayer, FlutterLocale,
+    FlutterOpenGLRendererConfig, FlutterPlatformMessage,
+    FlutterPlatformMessageCreateResponseHandle, FlutterPlatformMessageReleaseResponseHandle,
+    FlutterPlatformMessageResponseHandle, FlutterPointerDeviceKind,
+    FlutterPointerDeviceKind_kFlutterPointerDeviceKindMouse,
     FlutterPointerDeviceKind_kFlutterPointerDeviceKindStylus,
     FlutterPointerDeviceKind_kFlutterPointerDeviceKindTouch,
     FlutterPointerDeviceKind_kFlutterPointerDeviceKindTrackpad, FlutterPointerEvent,
@@ -35,24 +42,39 @@ use flutter_embedder::{
     FlutterPointerPhase_kAdd, FlutterPointerPhase_kDown, FlutterPointerPhase_kHover,
     FlutterPointerPhase_kMove, FlutterPointerPhase_kRemove, FlutterPointerPhase_kUp,
     FlutterPointerSignalKind_kFlutterPointerSignalKindScroll, FlutterPresentViewInfo,
-    FlutterProjectArgs, FlutterRendererConfig, FlutterRendererType_kOpenGL, FlutterTask,
-    FlutterTaskRunnerDescription, FlutterTransformation, FlutterWindowMetricsEvent,
-    FLUTTER_ENGINE_VERSION,
+    FlutterProjectArgs, FlutterRendererConfig, FlutterRendererType_kOpenGL,
+    FlutterSemanticsUpdate2, FlutterTask, FlutterTaskRunnerDescription, FlutterTransformation,
+    FlutterWindowMetricsEvent, FLUTTER_ENGINE_VERSION,
 };
 use parking_lot::Mutex;
 use smol_str::SmolStr;
 
+use crate::codec::{self, EncodableValue, ReadCursor};
 use crate::compositor::FlutterCompositor;
 use crate::egl::EglDevice;
-use crate::task_runner::{self, FlutterTaskRunner, Task};
+use crate::error::Error;
+use crate::locale::{self, Locale};
+use crate::panic_guard::{catch_panic, catch_panic_in_channel};
+use crate::semantics::{SemanticsActions, SemanticsHandler, SemanticsTree, SemanticsUpdate};
+use crate::standard_method_channel::StandardMethodCodec;
+use crate::task_runner::{self, FlutterTaskQueue, FlutterTaskRunner, Task};
 
 pub struct FlutterEngineConfig<'a> {
     pub assets_path: &'a str,
     pub aot_library_path: Option<&'a str>,
+    /// The Dart function to run instead of `main`.
+    pub dart_entrypoint: Option<&'a str>,
+    /// The arguments passed to the Dart entrypoint.
+    pub dart_entrypoint_args: &'a [String],
+    /// The command line switches passed to the engine. The first argument is the program name.
+    pub command_line_args: &'a [String],
     pub egl: Arc<EglDevice>,
     pub compositor: FlutterCompositor,
     pub platform_task_handler: Box<dyn Fn(Task)>,
     pub platform_message_handlers: Vec<(&'a str, Box<dyn BinaryMessageHandler + 'static>)>,
+    pub traffic_inspector: Option<Arc<TrafficInspector>>,
+    pub channel_buffer_size: usize,
+    pub semantics_handler: Option<Box<dyn SemanticsHandler>>,
 }
 
 pub struct FlutterEngine {
@@ -68,7 +90,10 @@ struct FlutterEngineInner {
     is_running: Arc<Mutex<bool>>,
     egl: Arc<EglDevice>,
     compositor: *mut FlutterCompositor,
-    platform_message_handlers: Mutex<BTreeMap<String, Box<dyn BinaryMessageHandler + 'static>>>,
+    platform_message_handlers: PlatformMessageHandlers,
+    traffic_inspector: Option<Arc<TrafficInspector>>,
+    semantics_tree: RefCell<SemanticsTree>,
+    semantics_handler: Option<Box<dyn SemanticsHandler>>,
 }
 
 #[derive(Clone, Copy, Default)]
@@ -136,7 +161,11 @@ pub struct KeyEvent {
 }
 
 impl FlutterEngine {
-    pub fn new(config: FlutterEngineConfig) -> eyre::Result<FlutterEngine> {
+    pub fn new(config: FlutterEngineConfig) -> crate::Result<FlutterEngine> {
+        if !Path::new(config.assets_path).is_dir() {
+            return Err(Error::MissingAssets(config.assets_path.into()));
+        }
+
         let platform_task_runner = create_task_runner(
             1,
             FlutterTaskRunner::new(move |task| (config.platform_task_handler)(task)),
@@ -160,9 +189,40 @@ impl FlutterEngine {
             },
         };
 
-        let assets_path = CString::from_str(config.assets_path)?;
+        let assets_path = CString::from_str(config.assets_path)
+            .map_err(|_| Error::InvalidPath(config.assets_path.into()))?;
         let aot_data = load_aot_data(config.aot_library_path)?.unwrap_or(ptr::null_mut());
 
+        let dart_entrypoint = config
+            .dart_entrypoint
+            .map(CString::from_str)
+            .transpose()
+            .map_err(Error::InvalidDartArgs)?;
+
+        let dart_entrypoint_args = config
+            .dart_entrypoint_args
+            .iter()
+            .map(|arg| CString::from_str(arg))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(Error::InvalidDartArgs)?;
+
+        let dart_entrypoint_argv = dart_entrypoint_args
+            .iter()
+            .map(|arg| arg.as_ptr())
+            .collect::<Vec<_>>();
+
+        let command_line_args = config
+            .command_line_args
+            .iter()
+            .map(|arg| CString::from_str(arg))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(Error::InvalidEngineSwitches)?;
+
+        let command_line_argv = command_line_args
+            .iter()
+            .map(|arg| arg.as_ptr())
+            .collect::<Vec<_>>();
+
         // This is freed when the FlutterEngine is dropped.
         let compositor = Box::into_raw(Box::new(config.compositor));
 
@@ -171,6 +231,13 @@ impl FlutterEngine {
             assets_path: assets_path.as_ptr(),
             icu_data_path: c"icudtl.dat".as_ptr(),
             aot_data,
+            command_line_argc: command_line_argv.len() as i32,
+            command_line_argv: command_line_argv.as_ptr(),
+            custom_dart_entrypoint: dart_entrypoint
+                .as_ref()
+                .map_or(ptr::null(), |entrypoint| entrypoint.as_ptr()),
+            dart_entrypoint_argc: dart_entrypoint_argv.len() as i32,
+            dart_entrypoint_argv: dart_entrypoint_argv.as_ptr(),
             custom_task_runners: &FlutterCustomTaskRunners {
                 struct_size: mem::size_of::<FlutterCustomTaskRunners>(),
                 platform_task_runner: &platform_task_runner,
@@ -189,24 +256,28 @@ impl FlutterEngine {
             },
             platform_message_callback: Some(platform_message_callback),
             log_message_callback: Some(log_message),
+            compute_platform_resolved_locale_callback: Some(compute_platform_resolved_locale),
+            update_semantics_callback2: Some(update_semantics),
             // vsync_callback: Some(vsync_callback),
             ..Default::default()
         };
 
-        let platform_message_handlers = BTreeMap::from_iter(
-            config
-                .platform_message_handlers
-                .into_iter()
-                .map(|(channel, handler)| (channel.to_owned(), handler)),
-        );
+        let platform_message_handlers =
+            PlatformMessageHandlers::new().with_buffer_size(config.channel_buffer_size);
+        for (channel, handler) in config.platform_message_handlers {
+            platform_message_handlers.set(channel, handler);
+        }
 
         // This is freed when the FlutterEngine is dropped.
         let engine = Box::leak(Box::new(FlutterEngineInner {
             handle: ptr::null_mut(),
             is_running: Arc::new(Mutex::new(false)),
             egl: config.egl,
-            platform_message_handlers: Mutex::new(platform_message_handlers),
+            platform_message_handlers,
             compositor,
+            traffic_inspector: config.traffic_inspector,
+            semantics_tree: RefCell::new(SemanticsTree::default()),
+            semantics_handler: config.semantics_handler,
         }));
 
         let engine_handle = unsafe {
@@ -221,7 +292,9 @@ impl FlutterEngine {
             );
 
             if result != FlutterEngineResult_kSuccess || engine_ptr.is_null() {
-                panic!("could not run the flutter engine");
+                drop(Box::from_raw(compositor));
+                drop(Box::from_raw(engine as *mut FlutterEngineInner));
+                return Err(Error::EngineInit(result));
             }
 
             engine_ptr
@@ -231,8 +304,17 @@ impl FlutterEngine {
 
         *engine.is_running.lock() = true;
 
-        unsafe {
-            FlutterEngineRunInitialized(engine_handle);
+        let result = unsafe { FlutterEngineRunInitialized(engine_handle) };
+        if result != FlutterEngineResult_kSuccess {
+            let engine = FlutterEngine {
+                inner: engine,
+                _not_send: PhantomData,
+            };
+
+            // Dropping the engine shuts it down and frees its state.
+            drop(engine);
+
+            return Err(Error::EngineInit(result));
         }
 
         Ok(FlutterEngine {
@@ -246,7 +328,7 @@ impl FlutterEngine {
         width: usize,
         height: usize,
         pixel_ratio: f64,
-    ) -> eyre::Result<()> {
+    ) -> crate::Result<()> {
         let result = unsafe {
             FlutterEngineSendWindowMetricsEvent(
                 self.inner.handle,
@@ -260,24 +342,16 @@ impl FlutterEngine {
             )
         };
 
-        if result != FlutterEngineResult_kSuccess {
-            bail!("failed to send window metrics event: {result}");
-        }
-
-        Ok(())
+        Error::check("FlutterEngineSendWindowMetricsEvent", result)
     }
 
-    pub fn run_task(&self, task: &FlutterTask) -> eyre::Result<()> {
+    pub fn run_task(&self, task: &FlutterTask) -> crate::Result<()> {
         let result = unsafe { FlutterEngineRunTask(self.inner.handle, task) };
 
-        if result != FlutterEngineResult_kSuccess {
-            bail!("failed to run task: {result}");
-        }
-
-        Ok(())
+        Error::check("FlutterEngineRunTask", result)
     }
 
-    pub fn send_pointer_event(&self, event: &PointerEvent) -> eyre::Result<()> {
+    pub fn send_pointer_event(&self, event: &PointerEvent) -> crate::Result<()> {
         let result = unsafe {
             FlutterEngineSendPointerEvent(
                 self.inner.handle,
@@ -296,11 +370,7 @@ impl FlutterEngine {
             )
         };
 
-        if result != FlutterEngineResult_kSuccess {
-            bail!("failed to send pointer event: {result}");
-        }
-
-        Ok(())
+        Error::check("FlutterEngineSendPointerEvent", result)
     }
 
     pub fn send_scroll_event(
@@ -309,7 +379,7 @@ impl FlutterEngine {
         y: f64,
         scroll_delta_x: f64,
         scroll_delta_y: f64,
-    ) -> eyre::Result<()> {
+    ) -> crate::Result<()> {
         let result = unsafe {
             FlutterEngineSendPointerEvent(
                 self.inner.handle,
@@ -327,14 +397,10 @@ impl FlutterEngine {
             )
         };
 
-        if result != FlutterEngineResult_kSuccess {
-            bail!("failed to send pointer event: {result}");
-        }
-
-        Ok(())
+        Error::check("FlutterEngineSendPointerEvent", result)
     }
 
-    pub fn send_key_event<F>(&self, event: &KeyEvent, callback: F) -> eyre::Result<()>
+    pub fn send_key_event<F>(&self, event: &KeyEvent, callback: F) -> crate::Result<()>
     where
         F: FnOnce(bool) + 'static,
     {
@@ -342,7 +408,9 @@ impl FlutterEngine {
             handled: bool,
             user_data: *mut ::std::os::raw::c_void,
         ) {
-            Box::from_raw(user_data.cast::<F>())(handled);
+            catch_panic("key_event_callback", || {
+                Box::from_raw(user_data.cast::<F>())(handled)
+            });
         }
 
         // This is freed above when the calback invoked.
@@ -371,9 +439,7 @@ impl FlutterEngine {
                 reply.cast(),
             );
 
-            if result != FlutterEngineResult_kSuccess {
-                bail!("failed to send key event: {result}");
-            }
+            Error::check("FlutterEngineSendKeyEvent", result)?;
         }
 
         Ok(())
@@ -383,18 +449,123 @@ impl FlutterEngine {
         &self,
         name: impl Into<String>,
         handler: impl BinaryMessageHandler + 'static,
-    ) {
+    ) -> Option<Rc<dyn BinaryMessageHandler>> {
         self.inner
             .platform_message_handlers
-            .lock()
-            .insert(name.into(), Box::new(handler));
+            .set(name, Box::new(handler))
+    }
+
+    pub fn remove_platform_message_handler(
+        &self,
+        name: &str,
+    ) -> Option<Rc<dyn BinaryMessageHandler>> {
+        self.inner.platform_message_handlers.remove(name)
+    }
+
+    pub fn resize_channel_buffer(&self, channel: &str, size: usize) {
+        self.inner
+            .platform_message_handlers
+            .resize_buffer(channel, size);
+    }
+
+    pub fn allow_channel_buffer_overflow(&self, channel: &str, allowed: bool) {
+        self.inner
+            .platform_message_handlers
+            .allow_buffer_overflow(channel, allowed);
     }
 
     pub fn messenger(&self) -> BinaryMessenger {
         BinaryMessenger {
             engine: self.inner.handle,
             engine_is_running: self.inner.is_running.clone(),
+            handlers: &self.inner.platform_message_handlers,
+            traffic_inspector: self.inner.traffic_inspector.clone(),
+        }
+    }
+
+    pub fn traffic_inspector(&self) -> Option<&TrafficInspector> {
+        self.inner.traffic_inspector.as_deref()
+    }
+
+    /// Sets the locales reported to Dart by `PlatformDispatcher.locales`, most preferred first.
+    pub fn update_locales(&self, locales: &[Locale]) -> crate::Result<()> {
+        *PREFERRED_LOCALES.lock() = locales.to_vec();
+
+        let strings = locales
+            .iter()
+            .filter_map(|locale| match locale_strings(locale) {
+                Ok(strings) => Some(strings),
+                Err(e) => {
+                    tracing::warn!("ignoring invalid locale {locale:?}: {e}");
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let as_ptr = |s: &Option<CString>| s.as_ref().map_or(ptr::null(), |s| s.as_ptr());
+
+        let flutter_locales = strings
+            .iter()
+            .map(|[language, country, script, variant]| FlutterLocale {
+                struct_size: mem::size_of::<FlutterLocale>(),
+                language_code: as_ptr(language),
+                country_code: as_ptr(country),
+                script_code: as_ptr(script),
+                variant_code: as_ptr(variant),
+            })
+            .collect::<Vec<_>>();
+
+        let mut flutter_locale_ptrs = flutter_locales
+            .iter()
+            .map(|locale| locale as *const FlutterLocale)
+            .collect::<Vec<_>>();
+
+        let result = unsafe {
+            FlutterEngineUpdateLocales(
+                self.inner.handle,
+                flutter_locale_ptrs.as_mut_ptr(),
+                flutter_locale_ptrs.len(),
+            )
+        };
+
+        Error::check("FlutterEngineUpdateLocales", result)
+    }
+
+    /// Enables or disables the semantics tree. While enabled, it is kept up to date and passed to
+    /// the semantics handler.
+    pub fn update_semantics_enabled(&self, enabled: bool) -> crate::Result<()> {
+        let result = unsafe { FlutterEngineUpdateSemanticsEnabled(self.inner.handle, enabled) };
+
+        if !enabled {
+            self.inner.semantics_tree.borrow_mut().clear();
         }
+
+        Error::check("FlutterEngineUpdateSemanticsEnabled", result)
+    }
+
+    /// Performs a semantics action on a node. `args` are the action's arguments encoded with the
+    /// standard message codec, or empty if it has none.
+    pub fn dispatch_semantics_action(
+        &self,
+        node_id: i32,
+        action: SemanticsActions,
+        args: &[u8],
+    ) -> crate::Result<()> {
+        let result = unsafe {
+            FlutterEngineDispatchSemanticsAction(
+                self.inner.handle,
+                node_id as u64,
+                action.bits(),
+                args.as_ptr(),
+                args.len(),
+            )
+        };
+
+        Error::check("FlutterEngineDispatchSemanticsAction", result)
+    }
+
+    pub fn semantics_tree(&self) -> Ref<'_, SemanticsTree> {
+        self.inner.semantics_tree.borrow()
     }
 
     pub fn schedule_frame(&self) {
@@ -422,21 +593,21 @@ impl Drop for FlutterEngine {
     }
 }
 
-fn load_aot_data(path: Option<&str>) -> eyre::Result<Option<FlutterEngineAOTData>> {
+fn load_aot_data(path: Option<&str>) -> crate::Result<Option<FlutterEngineAOTData>> {
     if !unsafe { FlutterEngineRunsAOTCompiledDartCode() } {
         tracing::debug!("Engine does not support AOT dart code");
         return Ok(None);
     }
 
     let Some(path) = path else {
-        bail!("No AOT library path was provided");
+        return Err(Error::MissingAotData(None));
     };
 
     if !Path::new(path).exists() {
-        bail!("AOT library not found at {path}");
+        return Err(Error::MissingAotData(Some(path.into())));
     }
 
-    let c_path = CString::from_str(path)?;
+    let c_path = CString::from_str(path).map_err(|_| Error::InvalidPath(path.into()))?;
     let source = FlutterEngineAOTDataSource {
         type_: FlutterEngineAOTDataSourceType_kFlutterEngineAOTDataSourceTypeElfPath,
         __bindgen_anon_1: FlutterEngineAOTDataSource__bindgen_ty_1 {
@@ -445,9 +616,12 @@ fn load_aot_data(path: Option<&str>) -> eyre::Result<Option<FlutterEngineAOTData
     };
 
     let mut aot_data = ptr::null_mut();
-    if unsafe { FlutterEngineCreateAOTData(&source, &mut aot_data) } != FlutterEngineResult_kSuccess
-    {
-        bail!("Failed to load AOT data from {path}");
+    let result = unsafe { FlutterEngineCreateAOTData(&source, &mut aot_data) };
+    if result != FlutterEngineResult_kSuccess {
+        return Err(Error::InvalidAotData {
+            path: path.into(),
+            result,
+        });
     }
 
     tracing::info!("Loaded AOT data from {path}");
@@ -460,11 +634,14 @@ fn create_task_runner<F: Fn(Task) + 'static>(
     runner: FlutterTaskRunner<F>,
 ) -> FlutterTaskRunnerDescription {
     unsafe extern "C" fn runs_tasks_on_current_thread<F>(task_runner: *mut c_void) -> bool {
-        task_runner
-            .cast::<FlutterTaskRunner<F>>()
-            .as_mut()
-            .unwrap()
-            .runs_tasks_on_current_thread()
+        catch_panic("runs_task_on_current_thread_callback", || {
+            task_runner
+                .cast::<FlutterTaskRunner<F>>()
+                .as_mut()
+                .unwrap()
+                .runs_tasks_on_current_thread()
+        })
+        .unwrap_or(false)
     }
 
     unsafe extern "C" fn post_task_callback<F: Fn(Task)>(
@@ -472,13 +649,17 @@ fn create_task_runner<F: Fn(Task) + 'static>(
         target_time_nanos: u64,
         user_data: *mut c_void,
     ) {
-        let runner = user_data.cast::<FlutterTaskRunner<F>>();
-        (*runner).post_task(task, target_time_nanos);
+        catch_panic("post_task_callback", || {
+            let runner = user_data.cast::<FlutterTaskRunner<F>>();
+            (*runner).post_task(task, target_time_nanos);
+        });
     }
 
     unsafe extern "C" fn destruction_callback<F: Fn(Task)>(user_data: *mut c_void) {
-        tracing::debug!("destroying task runner");
-        drop(Box::from_raw(user_data.cast::<FlutterTaskRunner<F>>()));
+        catch_panic("destruction_callback", || {
+            tracing::debug!("destroying task runner");
+            drop(Box::from_raw(user_data.cast::<FlutterTaskRunner<F>>()));
+        });
     }
 
     // This is freed by the above destruction_callback.
@@ -498,10 +679,42 @@ fn create_task_runner<F: Fn(Task) + 'static>(
 pub struct BinaryMessenger {
     engine: flutter_embedder::FlutterEngine,
     engine_is_running: Arc<Mutex<bool>>,
+    // Owned by the engine, and only valid while it is running.
+    handlers: *const PlatformMessageHandlers,
+    traffic_inspector: Option<Arc<TrafficInspector>>,
 }
 
 impl BinaryMessenger {
-    pub fn send_platform_message(&self, channel: &CStr, message: &[u8]) -> eyre::Result<()> {
+    /// Sets the handler for messages sent from Dart on `channel`, returning the previous handler if
+    /// there was one. See [PlatformMessageHandlers::set].
+    pub fn set_message_handler(
+        &self,
+        channel: impl Into<String>,
+        handler: impl BinaryMessageHandler + 'static,
+    ) -> crate::Result<Option<Rc<dyn BinaryMessageHandler>>> {
+        Ok(self.handlers()?.set(channel, Box::new(handler)))
+    }
+
+    /// Removes the handler for `channel`, returning it if there was one.
+    pub fn remove_message_handler(
+        &self,
+        channel: &str,
+    ) -> crate::Result<Option<Rc<dyn BinaryMessageHandler>>> {
+        Ok(self.handlers()?.remove(channel))
+    }
+
+    fn handlers(&self) -> crate::Result<&PlatformMessageHandlers> {
+        // The messenger can't be sent to another thread, so the engine can't be dropped while the
+        // handlers are borrowed.
+        if *self.engine_is_running.lock() {
+            Ok(unsafe { &*self.handlers })
+        } else {
+            Err(Error::MessengerNotRunning)
+        }
+    }
+
+    /// Sends a message without waiting for a reply. Empty messages are received as `null` in Dart.
+    pub fn send_platform_message(&self, channel: &CStr, message: &[u8]) -> crate::Result<()> {
         if *self.engine_is_running.lock() {
             unsafe {
                 let result = FlutterEngineSendPlatformMessage(
@@ -509,29 +722,107 @@ impl BinaryMessenger {
                     &FlutterPlatformMessage {
                         struct_size: mem::size_of::<FlutterPlatformMessage>(),
                         channel: channel.as_ptr(),
-                        message: message.as_ptr(),
+                        message: if message.is_empty() {
+                            ptr::null()
+                        } else {
+                            message.as_ptr()
+                        },
                         message_size: message.len(),
                         response_handle: ptr::null_mut(),
                     },
                 );
 
-                if result != FlutterEngineResult_kSuccess {
-                    bail!("failed to send platform message: {result}");
-                }
+                Error::check("FlutterEngineSendPlatformMessage", result)?;
+            }
 
-                Ok(())
+            if let Some(inspector) = &self.traffic_inspector {
+                inspector.record_message(
+                    TrafficDirection::Outbound,
+                    &channel.to_string_lossy(),
+                    message,
+                    false,
+                );
             }
+
+            Ok(())
         } else {
-            bail!("engine is not running")
+            Err(Error::MessengerNotRunning)
         }
     }
 
+    /// Sends a message, and calls `reply_handler` with the reply. The embedder delivers replies on
+    /// the platform task runner, so `reply_handler` is always called on the platform thread.
     pub fn send_platform_message_with_reply<F>(
         &self,
         channel: &CStr,
         message: &[u8],
         reply_handler: F,
-    ) -> eyre::Result<()>
+    ) -> crate::Result<()>
+    where
+        F: FnOnce(&[u8]) + 'static,
+    {
+        let Some(inspector) = self.traffic_inspector.clone() else {
+            return self.send_platform_message_with_reply_raw(channel, message, reply_handler);
+        };
+
+        // The message is recorded before sending, so that the reply can be attached to it.
+        let id = inspector.record_message(
+            TrafficDirection::Outbound,
+            &channel.to_string_lossy(),
+            message,
+            true,
+        );
+
+        let res = self.send_platform_message_with_reply_raw(channel, message, {
+            let inspector = inspector.clone();
+            move |reply: &[u8]| {
+                inspector.record_reply(id, reply);
+                reply_handler(reply);
+            }
+        });
+
+        if res.is_err() {
+            inspector.discard(id);
+        }
+
+        res
+    }
+
+    /// Resizes the buffer that Dart keeps for messages sent on `channel` before a handler is set on
+    /// the Dart side.
+    pub fn resize_channel_buffer(&self, channel: &str, size: usize) -> crate::Result<()> {
+        self.send_channel_buffers_command(
+            "resize",
+            vec![channel.into(), EncodableValue::I64(size as i64)],
+        )
+    }
+
+    /// Sets whether Dart should warn when messages on `channel` are dropped because its buffer is
+    /// full.
+    pub fn allow_channel_buffer_overflow(&self, channel: &str, allowed: bool) -> crate::Result<()> {
+        self.send_channel_buffers_command(
+            "overflow",
+            vec![channel.into(), EncodableValue::Bool(allowed)],
+        )
+    }
+
+    fn send_channel_buffers_command(
+        &self,
+        method: &str,
+        args: Vec<EncodableValue>,
+    ) -> crate::Result<()> {
+        let message = StandardMethodCodec::default()
+            .encode_method_call(method, &EncodableValue::List(args))
+            .map_err(Error::codec)?;
+        self.send_platform_message(c"dev.flutter/channel-buffers", &message)
+    }
+
+    fn send_platform_message_with_reply_raw<F>(
+        &self,
+        channel: &CStr,
+        message: &[u8],
+        reply_handler: F,
+    ) -> crate::Result<()>
     where
         F: FnOnce(&[u8]) + 'static,
     {
@@ -540,18 +831,24 @@ impl BinaryMessenger {
             size: usize,
             user_data: *mut ::std::os::raw::c_void,
         ) {
-            let reply_handler = Box::from_raw(user_data.cast::<F>());
-            if data.is_null() {
-                tracing::warn!("null reply from platform message");
-            } else {
-                reply_handler(std::slice::from_raw_parts(data, size));
-            }
+            catch_panic("platform_message_reply_callback", || {
+                let reply_handler = Box::from_raw(user_data.cast::<F>());
+                if data.is_null() {
+                    // The engine replies with null when there is no handler registered for the
+                    // channel on the Dart side. This is delivered as an empty reply, which handlers
+                    // for method channels treat as not implemented.
+                    reply_handler(&[]);
+                } else {
+                    reply_handler(std::slice::from_raw_parts(data, size));
+                }
+            });
         }
 
         if *self.engine_is_running.lock() {
             let mut response_handle = ptr::null_mut();
 
-            // This is freed by the callback above, when invoked.
+            // This is freed by the callback above when invoked, or below if the message couldn't
+            // be sent.
             let reply = Box::into_raw(Box::new(reply_handler));
             let result = unsafe {
                 FlutterPlatformMessageCreateResponseHandle(
@@ -562,8 +859,9 @@ impl BinaryMessenger {
                 )
             };
 
-            if result != FlutterEngineResult_kSuccess {
-                bail!("failed to create response handle: {result}");
+            if let Err(e) = Error::check("FlutterPlatformMessageCreateResponseHandle", result) {
+                drop(unsafe { Box::from_raw(reply) });
+                return Err(e);
             }
 
             let result = unsafe {
@@ -572,52 +870,398 @@ impl BinaryMessenger {
                     &FlutterPlatformMessage {
                         struct_size: mem::size_of::<FlutterPlatformMessage>(),
                         channel: channel.as_ptr(),
-                        message: message.as_ptr(),
+                        message: if message.is_empty() {
+                            ptr::null()
+                        } else {
+                            message.as_ptr()
+                        },
                         message_size: message.len(),
                         response_handle,
                     },
                 )
             };
 
-            if result != FlutterEngineResult_kSuccess {
-                bail!("failed to send platform message: {result}");
-            }
+            let sent = Error::check("FlutterEngineSendPlatformMessage", result);
 
             let result = unsafe {
                 FlutterPlatformMessageReleaseResponseHandle(self.engine, response_handle)
             };
 
-            if result != FlutterEngineResult_kSuccess {
-                bail!("failed to release response handle: {result}");
+            if let Err(e) = sent {
+                // The engine never invokes the callback for a message that wasn't sent.
+                drop(unsafe { Box::from_raw(reply) });
+                return Err(e);
             }
 
-            Ok(())
+            Error::check("FlutterPlatformMessageReleaseResponseHandle", result)
         } else {
-            bail!("engine is not running")
+            Err(Error::MessengerNotRunning)
+        }
+    }
+}
+
+/// A handle for sending platform messages from any thread. Messages are sent on the platform
+/// thread, and replies are also delivered there.
+#[derive(Clone)]
+pub struct ThreadSafeMessenger {
+    messenger: BinaryMessenger,
+    queue: Arc<FlutterTaskQueue>,
+}
+
+// SAFETY: The messenger is only used on the platform thread, by callbacks posted to the queue.
+unsafe impl Send for ThreadSafeMessenger {}
+unsafe impl Sync for ThreadSafeMessenger {}
+
+impl ThreadSafeMessenger {
+    pub(crate) fn new(messenger: BinaryMessenger, queue: Arc<FlutterTaskQueue>) -> Self {
+        ThreadSafeMessenger { messenger, queue }
+    }
+
+    /// Sends a message without waiting for a reply. Errors from sending the message on the
+    /// platform thread are logged.
+    pub fn send_platform_message(&self, channel: &str, message: Vec<u8>) -> crate::Result<()> {
+        let channel = CString::from_str(channel).map_err(Error::InvalidChannelName)?;
+        let this = self.clone();
+        self.queue.post(move || this.send_now(&channel, &message));
+        Ok(())
+    }
+
+    /// Sends a message, and calls `reply_handler` with the reply on the platform thread. If the
+    /// message can't be sent, the error is logged and `reply_handler` is dropped without being
+    /// called.
+    pub fn send_platform_message_with_reply<F>(
+        &self,
+        channel: &str,
+        message: Vec<u8>,
+        reply_handler: F,
+    ) -> crate::Result<()>
+    where
+        F: FnOnce(&[u8]) + Send + 'static,
+    {
+        let channel = CString::from_str(channel).map_err(Error::InvalidChannelName)?;
+        let this = self.clone();
+        self.queue
+            .post(move || this.send_with_reply_now(&channel, &message, reply_handler));
+        Ok(())
+    }
+
+    fn send_now(&self, channel: &CStr, message: &[u8]) {
+        if let Err(e) = self.messenger.send_platform_message(channel, message) {
+            tracing::error!(?channel, "failed to send platform message: {e}");
+        }
+    }
+
+    fn send_with_reply_now<F>(&self, channel: &CStr, message: &[u8], reply_handler: F)
+    where
+        F: FnOnce(&[u8]) + Send + 'static,
+    {
+        let res = self
+            .messenger
+            .send_platform_message_with_reply(channel, message, reply_handler);
+
+        if let Err(e) = res {
+            tracing::error!(?channel, "failed to send platform message: {e}");
         }
     }
 }
 
 pub trait BinaryMessageHandler {
     fn handle(&self, message: &[u8], reply: BinaryMessageReply);
+
+    /// Handles a message sent on `channel`. Handlers registered for a wildcard pattern can
+    /// override this to find out which of the matching channels the message was sent on. By
+    /// default, this calls [BinaryMessageHandler::handle].
+    fn handle_channel(&self, channel: &str, message: &[u8], reply: BinaryMessageReply) {
+        let _ = channel;
+        self.handle(message, reply)
+    }
+
+    /// The reply sent if [BinaryMessageHandler::handle] panics before replying. This is empty by
+    /// default, which is received as `null` in Dart. Method channels reply with an error instead.
+    fn panic_reply(&self, message: &str) -> Vec<u8> {
+        let _ = message;
+        vec![]
+    }
+}
+
+/// Platform message handlers, keyed by the channel that they handle.
+///
+/// A handler can also be set for every channel that starts with a prefix, using a pattern that ends
+/// with `*` such as `myplugin/player/*`. A handler set for the exact channel name takes precedence,
+/// followed by the pattern with the longest matching prefix.
+///
+/// Messages on channels without a handler can be buffered until one is set, like the channel
+/// buffers on the Dart side. Buffering is disabled unless a buffer size is set.
+#[derive(Default)]
+pub struct PlatformMessageHandlers {
+    handlers: Mutex<Handlers>,
+    buffers: Mutex<ChannelBuffers>,
+}
+
+#[derive(Default)]
+struct Handlers {
+    exact: BTreeMap<String, Rc<dyn BinaryMessageHandler + 'static>>,
+    // Keyed by the pattern without the trailing `*`.
+    prefixes: BTreeMap<String, Rc<dyn BinaryMessageHandler + 'static>>,
+}
+
+impl Handlers {
+    fn get(&self, channel: &str) -> Option<Rc<dyn BinaryMessageHandler>> {
+        if let Some(handler) = self.exact.get(channel) {
+            return Some(handler.clone());
+        }
+
+        // Every prefix of the channel sorts before it, and a longer prefix sorts after a shorter
+        // one, so the first prefix found in reverse order is the longest.
+        self.prefixes
+            .range::<str, _>((Bound::Unbounded, Bound::Included(channel)))
+            .rev()
+            .find(|(prefix, _)| channel.starts_with(prefix.as_str()))
+            .map(|(_, handler)| handler.clone())
+    }
+
+    fn entries(
+        &mut self,
+        channel: &str,
+    ) -> (&mut BTreeMap<String, Rc<dyn BinaryMessageHandler>>, String) {
+        match channel.strip_suffix('*') {
+            Some(prefix) => (&mut self.prefixes, prefix.to_owned()),
+            None => (&mut self.exact, channel.to_owned()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ChannelBuffers {
+    default_size: usize,
+    channels: HashMap<String, ChannelBuffer>,
+}
+
+#[derive(Default)]
+struct ChannelBuffer {
+    size: Option<usize>,
+    allow_overflow: bool,
+    messages: VecDeque<(Vec<u8>, BinaryMessageReply)>,
+}
+
+impl PlatformMessageHandlers {
+    pub fn new() -> PlatformMessageHandlers {
+        PlatformMessageHandlers::default()
+    }
+
+    /// Sets the number of messages buffered for each channel that has no handler, unless resized
+    /// with [PlatformMessageHandlers::resize_buffer].
+    pub fn with_buffer_size(self, size: usize) -> Self {
+        self.buffers.lock().default_size = size;
+        self
+    }
+
+    /// Sets the handler for `channel`, which may be a wildcard pattern, and passes it any messages
+    /// that were buffered for the matching channels. Returns the previous handler for `channel`, if
+    /// any.
+    pub fn set(
+        &self,
+        channel: impl Into<String>,
+        handler: Box<dyn BinaryMessageHandler>,
+    ) -> Option<Rc<dyn BinaryMessageHandler>> {
+        let channel = channel.into();
+
+        let previous = {
+            let mut handlers = self.handlers.lock();
+            let (entries, key) = handlers.entries(&channel);
+            entries.insert(key, Rc::from(handler))
+        };
+
+        let prefix = channel.strip_suffix('*');
+        let mut buffered = self
+            .buffers
+            .lock()
+            .channels
+            .iter_mut()
+            .filter(|(name, _)| match prefix {
+                Some(prefix) => name.starts_with(prefix),
+                None => **name == channel,
+            })
+            .map(|(name, buffer)| (name.clone(), mem::take(&mut buffer.messages)))
+            .collect::<Vec<_>>();
+
+        buffered.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        for (channel, messages) in buffered {
+            for (message, reply) in messages {
+                self.dispatch(&channel, &message, reply);
+            }
+        }
+
+        previous
+    }
+
+    /// Removes the handler for `channel`, which may be a wildcard pattern, returning it if there
+    /// was one. Subsequent messages on the channel are passed to the next matching handler, or
+    /// buffered if there is none.
+    pub fn remove(&self, channel: &str) -> Option<Rc<dyn BinaryMessageHandler>> {
+        let mut handlers = self.handlers.lock();
+        let (entries, key) = handlers.entries(channel);
+        entries.remove(&key)
+    }
+
+    /// Sets the number of messages buffered for `channel` while it has no handler. If more messages
+    /// are already buffered, the oldest ones are dropped.
+    pub fn resize_buffer(&self, channel: &str, size: usize) {
+        let overflowed = {
+            let mut buffers = self.buffers.lock();
+            let buffer = buffers.channels.entry(channel.to_owned()).or_default();
+            buffer.size = Some(size);
+            buffer.drain_overflow(size)
+        };
+
+        drop_overflowed(channel, overflowed, false);
+    }
+
+    /// Sets whether dropping messages on `channel` when its buffer is full is expected. If not, a
+    /// warning is logged for each dropped message.
+    pub fn allow_buffer_overflow(&self, channel: &str, allowed: bool) {
+        self.buffers
+            .lock()
+            .channels
+            .entry(channel.to_owned())
+            .or_default()
+            .allow_overflow = allowed;
+    }
+
+    /// Passes a message to the handler for `channel`. If there is no handler, the message is
+    /// buffered, or replied to as not implemented if the channel's buffer size is zero. If the
+    /// handler panics before replying, the message is replied to with the handler's
+    /// [BinaryMessageHandler::panic_reply].
+    pub fn dispatch(&self, channel: &str, message: &[u8], reply: BinaryMessageReply) {
+        // The lock is released before calling the handler, so that it can set or remove handlers.
+        let handler = self.handlers.lock().get(channel);
+        let Some(handler) = handler else {
+            self.buffer(channel, message, reply);
+            return;
+        };
+
+        let fallback = reply.fallback();
+        let res = catch_panic_in_channel("platform_message_callback", Some(channel), || {
+            handler.handle_channel(channel, message, reply)
+        });
+
+        // If the reply was dropped while unwinding, reply on the handler's behalf so that Dart
+        // doesn't wait forever. If it's still alive, e.g. held by a pending future, it may yet be
+        // sent.
+        if let Err(panic_message) = res
+            && !fallback.replied.load(Ordering::Acquire)
+            && Arc::strong_count(&fallback.replied) == 1
+        {
+            let bytes = handler.panic_reply(&panic_message);
+            if bytes.is_empty() {
+                fallback.not_implemented();
+            } else {
+                fallback.send(&bytes);
+            }
+        }
+    }
+
+    fn buffer(&self, channel: &str, message: &[u8], reply: BinaryMessageReply) {
+        let (overflowed, allow_overflow) = {
+            let mut buffers = self.buffers.lock();
+            let default_size = buffers.default_size;
+
+            let size = buffers
+                .channels
+                .get(channel)
+                .and_then(|buffer| buffer.size)
+                .unwrap_or(default_size);
+
+            if size == 0 {
+                drop(buffers);
+                tracing::warn!(channel, "unimplemented");
+                reply.not_implemented();
+                return;
+            }
+
+            let buffer = buffers.channels.entry(channel.to_owned()).or_default();
+
+            tracing::debug!(channel, "buffering message until a handler is set");
+
+            buffer.messages.push_back((message.to_vec(), reply));
+            (buffer.drain_overflow(size), buffer.allow_overflow)
+        };
+
+        drop_overflowed(channel, overflowed, allow_overflow);
+    }
+}
+
+impl ChannelBuffer {
+    fn drain_overflow(&mut self, size: usize) -> Vec<BinaryMessageReply> {
+        let overflow = self.messages.len().saturating_sub(size);
+        self.messages
+            .drain(..overflow)
+            .map(|(_, reply)| reply)
+            .collect()
+    }
+}
+
+fn drop_overflowed(channel: &str, overflowed: Vec<BinaryMessageReply>, allow_overflow: bool) {
+    for reply in overflowed {
+        if !allow_overflow {
+            tracing::warn!(
+                channel,
+                "channel buffer is full, dropping the oldest message"
+            );
+        }
+
+        // Replies are sent after releasing the lock, since they may call back into the handlers.
+        reply.not_implemented();
+    }
 }
 
 pub struct BinaryMessageReply {
-    engine: flutter_embedder::FlutterEngine,
-    engine_is_running: Arc<Mutex<bool>>,
-    response_handle: *const FlutterPlatformMessageResponseHandle,
+    target: ReplyTarget,
+    // Set once the reply has been sent, so that a reply can be sent on the handler's behalf if it
+    // panics.
+    replied: Arc<AtomicBool>,
 }
 
+enum ReplyTarget {
+    Engine {
+        engine: flutter_embedder::FlutterEngine,
+        engine_is_running: Arc<Mutex<bool>>,
+        response_handle: *const FlutterPlatformMessageResponseHandle,
+        traffic_inspector: Option<Arc<TrafficInspector>>,
+    },
+    // Shared with the fallback created for a panicking handler, which takes the callback only if
+    // the handler hasn't.
+    Callback(Rc<RefCell<Option<ReplyCallback>>>),
+}
+
+type ReplyCallback = Box<dyn FnOnce(&[u8])>;
+
 impl BinaryMessageReply {
     pub(crate) fn new(
         engine: flutter_embedder::FlutterEngine,
         engine_is_running: Arc<Mutex<bool>>,
         response_handle: *const FlutterPlatformMessageResponseHandle,
+        traffic_inspector: Option<Arc<TrafficInspector>>,
     ) -> BinaryMessageReply {
         BinaryMessageReply {
-            engine,
-            engine_is_running,
-            response_handle,
+            target: ReplyTarget::Engine {
+                engine,
+                engine_is_running,
+                response_handle,
+                traffic_inspector,
+            },
+            replied: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Creates a reply that is passed to `callback` instead of being sent to the engine, e.g. to
+    /// test a handler without running an engine. A not implemented reply is passed as an empty
+    /// message.
+    pub fn from_fn(callback: impl FnOnce(&[u8]) + 'static) -> BinaryMessageReply {
+        BinaryMessageReply {
+            target: ReplyTarget::Callback(Rc::new(RefCell::new(Some(Box::new(callback))))),
+            replied: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -629,6 +1273,7 @@ impl BinaryMessageReply {
             engine.inner.handle,
             engine.inner.is_running.clone(),
             response_handle,
+            engine.inner.traffic_inspector.clone(),
         )
     }
 
@@ -640,23 +1285,83 @@ impl BinaryMessageReply {
         self.send_raw(ptr::null(), 0);
     }
 
+    /// Returns a second reply to the same message, sharing the `replied` flag.
+    fn fallback(&self) -> BinaryMessageReply {
+        let target = match &self.target {
+            ReplyTarget::Engine {
+                engine,
+                engine_is_running,
+                response_handle,
+                traffic_inspector,
+            } => ReplyTarget::Engine {
+                engine: *engine,
+                engine_is_running: engine_is_running.clone(),
+                response_handle: *response_handle,
+                traffic_inspector: traffic_inspector.clone(),
+            },
+            ReplyTarget::Callback(callback) => ReplyTarget::Callback(callback.clone()),
+        };
+
+        BinaryMessageReply {
+            target,
+            replied: self.replied.clone(),
+        }
+    }
+
     fn send_raw(self, data: *const u8, length: usize) {
-        if *self.engine_is_running.lock() {
-            unsafe {
-                FlutterEngineSendPlatformMessageResponse(
-                    self.engine,
-                    self.response_handle,
-                    data,
-                    length,
-                );
-            }
+        self.replied.store(true, Ordering::Release);
+
+        let reply: &[u8] = if data.is_null() {
+            &[]
         } else {
-            tracing::error!("send called on a stopped engine");
+            unsafe { std::slice::from_raw_parts(data, length) }
+        };
+
+        match self.target {
+            ReplyTarget::Engine {
+                engine,
+                engine_is_running,
+                response_handle,
+                traffic_inspector,
+            } => {
+                if let Some(inspector) = &traffic_inspector {
+                    inspector.record_inbound_reply(response_handle, reply);
+                }
+
+                if *engine_is_running.lock() {
+                    unsafe {
+                        FlutterEngineSendPlatformMessageResponse(
+                            engine,
+                            response_handle,
+                            data,
+                            length,
+                        );
+                    }
+                } else {
+                    tracing::error!("send called on a stopped engine");
+                }
+            }
+            ReplyTarget::Callback(callback) => {
+                if let Some(callback) = callback.take() {
+                    callback(reply);
+                }
+            }
         }
     }
 
+    /// Returns the engine's response handle, which must then be used to reply. This is null if the
+    /// reply isn't sent to an engine.
     pub(crate) fn into_raw(self) -> *const FlutterPlatformMessageResponseHandle {
-        self.response_handle
+        self.replied.store(true, Ordering::Release);
+        match self.target {
+            ReplyTarget::Engine {
+                response_handle, ..
+            } => response_handle,
+            ReplyTarget::Callback(_) => {
+                tracing::error!("reply is not backed by a response handle");
+                ptr::null()
+            }
+        }
     }
 }
 
@@ -664,6 +1369,12 @@ unsafe extern "C" fn platform_message_callback(
     message: *const FlutterPlatformMessage,
     user_data: *mut c_void,
 ) {
+    catch_panic("platform_message_callback", || {
+        handle_platform_message(message, user_data)
+    });
+}
+
+unsafe fn handle_platform_message(message: *const FlutterPlatformMessage, user_data: *mut c_void) {
     let engine = user_data.cast::<FlutterEngineInner>().as_ref().unwrap();
     let message = message.as_ref().unwrap();
 
@@ -671,6 +1382,7 @@ unsafe extern "C" fn platform_message_callback(
         engine.handle,
         engine.is_running.clone(),
         message.response_handle,
+        engine.traffic_inspector.clone(),
     );
 
     let channel = CStr::from_ptr(message.channel);
@@ -680,55 +1392,384 @@ unsafe extern "C" fn platform_message_callback(
         return;
     };
 
-    let handlers = engine.platform_message_handlers.lock();
-    let Some(handler) = handlers.get(channel) else {
-        tracing::warn!(channel, "unimplemented");
-        reply.not_implemented();
-        return;
+    let bytes: &[u8] = if message.message.is_null() {
+        &[]
+    } else {
+        std::slice::from_raw_parts(message.message, message.message_size)
     };
 
+    if let Some(inspector) = &engine.traffic_inspector {
+        inspector.record_inbound(channel, bytes, message.response_handle);
+    }
+
     if message.message.is_null() {
         tracing::error!(channel, "message is null");
         reply.not_implemented();
         return;
     }
 
-    let bytes = std::slice::from_raw_parts(message.message, message.message_size);
+    engine
+        .platform_message_handlers
+        .dispatch(channel, bytes, reply);
+}
 
-    handler.handle(bytes, reply);
+/// How the payloads on a channel are decoded by the [TrafficInspector].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PayloadCodec {
+    /// Payloads are shown as raw bytes.
+    Binary,
+    /// The standard message codec. Method calls and envelopes are shown as the sequence of values
+    /// that they contain, e.g. the method name followed by the arguments.
+    Standard,
+    /// The JSON message codec.
+    Json,
 }
 
-unsafe extern "C" fn gl_make_current(user_data: *mut c_void) -> bool {
-    let engine = user_data.cast::<FlutterEngineInner>().as_ref().unwrap();
+impl PayloadCodec {
+    const MAX_DECODED_LEN: usize = 4096;
+
+    fn decode(self, bytes: &[u8]) -> String {
+        if bytes.is_empty() {
+            return String::from("<empty>");
+        }
+
+        let mut decoded = match self {
+            PayloadCodec::Binary => format!("{bytes:02x?}"),
+            PayloadCodec::Standard => {
+                let mut cursor = ReadCursor::new(bytes);
+                let mut values = vec![];
+                while (cursor.position() as usize) < bytes.len() {
+                    match codec::read_value(&mut cursor) {
+                        Ok(value) => values.push(format!("{value:?}")),
+                        Err(e) => {
+                            values.push(format!("<decode error: {e}>"));
+                            break;
+                        }
+                    }
+                }
+                values.join(", ")
+            }
+            PayloadCodec::Json => match serde_json::from_slice::<serde_json::Value>(bytes) {
+                Ok(value) => value.to_string(),
+                Err(e) => format!("<decode error: {e}>"),
+            },
+        };
 
-    if let Err(e) = engine.egl.make_context_current() {
-        tracing::error!("failed to make context current: {e}");
-        return false;
+        if decoded.len() > Self::MAX_DECODED_LEN {
+            let mut end = Self::MAX_DECODED_LEN;
+            while !decoded.is_char_boundary(end) {
+                end -= 1;
+            }
+            decoded.truncate(end);
+            decoded.push_str("...");
+        }
+
+        decoded
     }
+}
 
-    true
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrafficDirection {
+    /// A message sent by Dart to a handler in Rust.
+    Inbound,
+    /// A message sent by Rust to Dart.
+    Outbound,
 }
 
-unsafe extern "C" fn gl_make_resource_current(user_data: *mut c_void) -> bool {
-    let engine = user_data.cast::<FlutterEngineInner>().as_ref().unwrap();
+/// A platform message recorded by the [TrafficInspector].
+#[derive(Clone, Debug)]
+pub struct TrafficRecord {
+    pub id: u64,
+    pub direction: TrafficDirection,
+    pub channel: String,
+    pub timestamp: SystemTime,
+    pub message: Vec<u8>,
+    /// The message decoded with the channel's [PayloadCodec].
+    pub payload: String,
+    /// The reply to the message, once it has been sent (for inbound messages) or received (for
+    /// outbound messages).
+    pub reply: Option<TrafficReply>,
+    expects_reply: bool,
+    sent_at: Instant,
+}
 
-    if let Err(e) = engine.egl.make_resource_context_current() {
-        tracing::error!("failed to make resource context current: {e}");
-        return false;
+impl TrafficRecord {
+    /// Returns true if the message is still waiting for a reply.
+    pub fn is_pending(&self) -> bool {
+        self.expects_reply && self.reply.is_none()
     }
+}
 
-    true
+#[derive(Clone, Debug)]
+pub struct TrafficReply {
+    pub timestamp: SystemTime,
+    /// The time between the message being sent and the reply.
+    pub latency: Duration,
+    pub message: Vec<u8>,
+    pub payload: String,
 }
 
-unsafe extern "C" fn gl_clear_current(user_data: *mut c_void) -> bool {
-    let engine = user_data.cast::<FlutterEngineInner>().as_ref().unwrap();
+/// Records the platform messages exchanged with Dart, for debugging. Each message and reply is
+/// also logged as a `debug` event with the `flion::traffic` target.
+///
+/// Only the most recent messages are kept, up to the inspector's capacity.
+pub struct TrafficInspector {
+    capacity: usize,
+    codecs: HashMap<String, PayloadCodec>,
+    state: Mutex<TrafficState>,
+}
+
+#[derive(Default)]
+struct TrafficState {
+    next_id: u64,
+    records: VecDeque<TrafficRecord>,
+    // Maps the response handles of inbound messages to their records. Replies may be sent through
+    // a different BinaryMessageReply with the same handle, e.g. by plugins.
+    pending_inbound: HashMap<usize, u64>,
+}
+
+impl TrafficInspector {
+    /// Creates an inspector that keeps up to `capacity` messages. Payloads on the engine's built-in
+    /// channels are decoded with the codec used by the framework. Other channels are shown as raw
+    /// bytes unless set with [TrafficInspector::with_channel_codec].
+    pub fn new(capacity: usize) -> TrafficInspector {
+        let codecs = [
+            ("dev.flutter/channel-buffers", PayloadCodec::Standard),
+            ("flutter/keyevent", PayloadCodec::Json),
+            ("flutter/mousecursor", PayloadCodec::Standard),
+            ("flutter/platform", PayloadCodec::Json),
+            ("flutter/settings", PayloadCodec::Json),
+            ("flutter/textinput", PayloadCodec::Json),
+            ("flion/platform_views", PayloadCodec::Standard),
+        ];
+
+        TrafficInspector {
+            capacity,
+            codecs: codecs
+                .into_iter()
+                .map(|(channel, codec)| (channel.to_owned(), codec))
+                .collect(),
+            state: Mutex::new(TrafficState::default()),
+        }
+    }
+
+    /// Sets the codec used to decode payloads on `channel`.
+    pub fn with_channel_codec(mut self, channel: impl Into<String>, codec: PayloadCodec) -> Self {
+        self.codecs.insert(channel.into(), codec);
+        self
+    }
+
+    /// Returns all recorded messages, oldest first.
+    pub fn records(&self) -> Vec<TrafficRecord> {
+        self.state.lock().records.iter().cloned().collect()
+    }
+
+    /// Returns the recorded messages on `channel`, oldest first.
+    pub fn channel_records(&self, channel: &str) -> Vec<TrafficRecord> {
+        self.state
+            .lock()
+            .records
+            .iter()
+            .filter(|record| record.channel == channel)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the recorded messages that are still waiting for a reply.
+    pub fn pending(&self) -> Vec<TrafficRecord> {
+        self.state
+            .lock()
+            .records
+            .iter()
+            .filter(|record| record.is_pending())
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        let mut state = self.state.lock();
+        state.records.clear();
+        state.pending_inbound.clear();
+    }
+
+    fn codec(&self, channel: &str) -> PayloadCodec {
+        self.codecs
+            .get(channel)
+            .copied()
+            .unwrap_or(PayloadCodec::Binary)
+    }
+
+    /// Decodes a payload, unless it would be neither recorded nor logged.
+    fn decode(&self, channel: &str, bytes: &[u8], recorded: bool) -> String {
+        if recorded || tracing::enabled!(target: "flion::traffic", tracing::Level::DEBUG) {
+            self.codec(channel).decode(bytes)
+        } else {
+            String::new()
+        }
+    }
 
-    if let Err(e) = engine.egl.clear_current() {
-        tracing::error!("failed to clear context: {e}");
-        return false;
+    fn record_message(
+        &self,
+        direction: TrafficDirection,
+        channel: &str,
+        message: &[u8],
+        expects_reply: bool,
+    ) -> u64 {
+        let payload = self.decode(channel, message, self.capacity > 0);
+
+        let mut state = self.state.lock();
+
+        let id = state.next_id;
+        state.next_id += 1;
+
+        tracing::debug!(
+            target: "flion::traffic",
+            id,
+            ?direction,
+            channel,
+            size = message.len(),
+            payload,
+            "platform message"
+        );
+
+        if state.records.len() >= self.capacity
+            && let Some(evicted) = state.records.pop_front()
+            && evicted.direction == TrafficDirection::Inbound
+            && evicted.is_pending()
+        {
+            // Replies to evicted messages can't be recorded, so their handles aren't kept either.
+            state.pending_inbound.retain(|_, id| *id != evicted.id);
+        }
+
+        if self.capacity > 0 {
+            state.records.push_back(TrafficRecord {
+                id,
+                direction,
+                channel: channel.to_owned(),
+                timestamp: SystemTime::now(),
+                message: message.to_vec(),
+                payload,
+                reply: None,
+                expects_reply,
+                sent_at: Instant::now(),
+            });
+        }
+
+        id
+    }
+
+    fn record_inbound(
+        &self,
+        channel: &str,
+        message: &[u8],
+        response_handle: *const FlutterPlatformMessageResponseHandle,
+    ) {
+        let id = self.record_message(TrafficDirection::Inbound, channel, message, true);
+        if self.capacity > 0 {
+            self.state
+                .lock()
+                .pending_inbound
+                .insert(response_handle as usize, id);
+        }
     }
 
-    true
+    fn record_inbound_reply(
+        &self,
+        response_handle: *const FlutterPlatformMessageResponseHandle,
+        reply: &[u8],
+    ) {
+        let id = self
+            .state
+            .lock()
+            .pending_inbound
+            .remove(&(response_handle as usize));
+
+        if let Some(id) = id {
+            self.record_reply(id, reply);
+        }
+    }
+
+    fn record_reply(&self, id: u64, reply: &[u8]) {
+        let mut state = self.state.lock();
+
+        // The message may have already been evicted, in which case only the event is logged.
+        let record = match state.records.binary_search_by_key(&id, |record| record.id) {
+            Ok(index) => state.records.get_mut(index),
+            Err(_) => None,
+        };
+
+        let (channel, latency) = match &record {
+            Some(record) => (record.channel.clone(), record.sent_at.elapsed()),
+            None => (String::new(), Duration::ZERO),
+        };
+
+        let payload = self.decode(&channel, reply, record.is_some());
+
+        tracing::debug!(
+            target: "flion::traffic",
+            id,
+            channel,
+            latency_us = latency.as_micros() as u64,
+            size = reply.len(),
+            payload,
+            "platform message reply"
+        );
+
+        if let Some(record) = record {
+            record.reply = Some(TrafficReply {
+                timestamp: SystemTime::now(),
+                latency,
+                message: reply.to_vec(),
+                payload,
+            });
+        }
+    }
+
+    fn discard(&self, id: u64) {
+        self.state.lock().records.retain(|record| record.id != id);
+    }
+}
+
+unsafe extern "C" fn gl_make_current(user_data: *mut c_void) -> bool {
+    catch_panic("gl_make_current", || {
+        let engine = user_data.cast::<FlutterEngineInner>().as_ref().unwrap();
+
+        if let Err(e) = engine.egl.make_context_current() {
+            tracing::error!("failed to make context current: {e}");
+            return false;
+        }
+
+        true
+    })
+    .unwrap_or(false)
+}
+
+unsafe extern "C" fn gl_make_resource_current(user_data: *mut c_void) -> bool {
+    catch_panic("gl_make_resource_current", || {
+        let engine = user_data.cast::<FlutterEngineInner>().as_ref().unwrap();
+
+        if let Err(e) = engine.egl.make_resource_context_current() {
+            tracing::error!("failed to make resource context current: {e}");
+            return false;
+        }
+
+        true
+    })
+    .unwrap_or(false)
+}
+
+unsafe extern "C" fn gl_clear_current(user_data: *mut c_void) -> bool {
+    catch_panic("gl_clear_current", || {
+        let engine = user_data.cast::<FlutterEngineInner>().as_ref().unwrap();
+
+        if let Err(e) = engine.egl.clear_current() {
+            tracing::error!("failed to clear context: {e}");
+            return false;
+        }
+
+        true
+    })
+    .unwrap_or(false)
 }
 
 unsafe extern "C" fn gl_present(_user_data: *mut c_void) -> bool {
@@ -743,31 +1784,33 @@ unsafe extern "C" fn gl_get_proc_address(
     user_data: *mut c_void,
     name: *const c_char,
 ) -> *mut c_void {
-    let engine = user_data.cast::<FlutterEngineInner>().as_ref().unwrap();
-    let name = CStr::from_ptr(name);
-    engine
-        .egl
-        .get_proc_address(name.to_str().unwrap())
-        .unwrap_or(ptr::null_mut())
+    catch_panic("gl_proc_resolver", || {
+        let engine = user_data.cast::<FlutterEngineInner>().as_ref().unwrap();
+        let name = CStr::from_ptr(name);
+        engine.egl.get_proc_address(name.to_str().unwrap())
+    })
+    .flatten()
+    .unwrap_or(ptr::null_mut())
 }
 
 unsafe extern "C" fn gl_get_surface_transformation(
     user_data: *mut c_void,
 ) -> FlutterTransformation {
-    let engine = user_data.cast::<FlutterEngineInner>().as_ref().unwrap();
-    let compositor = engine.compositor.as_mut().unwrap();
-    match compositor.get_surface_transformation() {
-        Ok(transformation) => transformation,
-        Err(e) => {
-            tracing::error!("failed to get surface transformation: {e:?}");
-            FlutterTransformation {
-                scaleX: 1.0,
-                scaleY: 1.0,
-                pers2: 1.0,
-                ..Default::default()
-            }
-        }
-    }
+    let transformation = catch_panic("gl_get_surface_transformation", || {
+        let engine = user_data.cast::<FlutterEngineInner>().as_ref().unwrap();
+        let compositor = engine.compositor.as_mut().unwrap();
+        compositor
+            .get_surface_transformation()
+            .inspect_err(|e| tracing::error!("failed to get surface transformation: {e:?}"))
+            .ok()
+    });
+
+    transformation.flatten().unwrap_or(FlutterTransformation {
+        scaleX: 1.0,
+        scaleY: 1.0,
+        pers2: 1.0,
+        ..Default::default()
+    })
 }
 
 pub unsafe extern "C" fn compositor_create_backing_store(
@@ -775,82 +1818,161 @@ pub unsafe extern "C" fn compositor_create_backing_store(
     out: *mut FlutterBackingStore,
     user_data: *mut c_void,
 ) -> bool {
-    let Some(compositor) = user_data.cast::<FlutterCompositor>().as_mut() else {
-        tracing::error!("user_data is null");
-        return false;
-    };
+    catch_panic("compositor_create_backing_store", || {
+        let Some(compositor) = user_data.cast::<FlutterCompositor>().as_mut() else {
+            tracing::error!("user_data is null");
+            return false;
+        };
 
-    let Some(config) = config.as_ref() else {
-        tracing::error!("config is null");
-        return false;
-    };
+        let Some(config) = config.as_ref() else {
+            tracing::error!("config is null");
+            return false;
+        };
 
-    let Some(backing_store) = out.as_mut() else {
-        tracing::error!("out is null");
-        return false;
-    };
+        let Some(backing_store) = out.as_mut() else {
+            tracing::error!("out is null");
+            return false;
+        };
 
-    if let Err(e) = compositor.create_backing_store(config, backing_store) {
-        tracing::error!("{e:?}");
-        return false;
-    }
+        if let Err(e) = compositor.create_backing_store(config, backing_store) {
+            tracing::error!("{e:?}");
+            return false;
+        }
 
-    true
+        true
+    })
+    .unwrap_or(false)
 }
 
 pub unsafe extern "C" fn compositor_collect_backing_store(
     backing_store: *const FlutterBackingStore,
     user_data: *mut c_void,
 ) -> bool {
-    let Some(compositor) = user_data.cast::<FlutterCompositor>().as_mut() else {
-        tracing::error!("user_data is null");
-        return false;
-    };
+    catch_panic("compositor_collect_backing_store", || {
+        let Some(compositor) = user_data.cast::<FlutterCompositor>().as_mut() else {
+            tracing::error!("user_data is null");
+            return false;
+        };
 
-    let Some(backing_store) = backing_store.as_ref() else {
-        tracing::error!("config is null");
-        return false;
-    };
+        let Some(backing_store) = backing_store.as_ref() else {
+            tracing::error!("config is null");
+            return false;
+        };
 
-    if let Err(e) = compositor.collect_backing_store(backing_store) {
-        tracing::error!("{e:?}");
-        return false;
-    }
+        if let Err(e) = compositor.collect_backing_store(backing_store) {
+            tracing::error!("{e:?}");
+            return false;
+        }
 
-    true
+        true
+    })
+    .unwrap_or(false)
 }
 
 pub unsafe extern "C" fn compositor_present_view(info: *const FlutterPresentViewInfo) -> bool {
-    let Some(info) = info.as_ref() else {
-        tracing::error!("FlutterPresentViewInfo is invalid");
-        return false;
-    };
+    catch_panic("compositor_present_view", || {
+        let Some(info) = info.as_ref() else {
+            tracing::error!("FlutterPresentViewInfo is invalid");
+            return false;
+        };
 
-    let Some(compositor) = info.user_data.cast::<FlutterCompositor>().as_mut() else {
-        tracing::error!("user_data is null");
-        return false;
-    };
+        let Some(compositor) = info.user_data.cast::<FlutterCompositor>().as_mut() else {
+            tracing::error!("user_data is null");
+            return false;
+        };
 
-    if info.layers.is_null() {
-        tracing::error!("layers is null");
-        return false;
-    }
+        if info.layers.is_null() {
+            tracing::error!("layers is null");
+            return false;
+        }
 
-    let layers = std::slice::from_raw_parts(info.layers.cast::<&FlutterLayer>(), info.layers_count);
+        let layers =
+            std::slice::from_raw_parts(info.layers.cast::<&FlutterLayer>(), info.layers_count);
 
-    if let Err(e) = compositor
-        .present_view(info.view_id, layers)
-        .wrap_err("Failed to present view")
-    {
-        tracing::error!("{e:?}");
-        return false;
-    };
+        if let Err(e) = compositor
+            .present_view(info.view_id, layers)
+            .wrap_err("Failed to present view")
+        {
+            tracing::error!("{e:?}");
+            return false;
+        };
+
+        true
+    })
+    .unwrap_or(false)
+}
+
+fn locale_strings(locale: &Locale) -> Result<[Option<CString>; 4], NulError> {
+    let optional = |code: &Option<String>| code.as_deref().map(CString::from_str).transpose();
+
+    Ok([
+        Some(CString::from_str(&locale.language_code)?),
+        optional(&locale.country_code)?,
+        optional(&locale.script_code)?,
+        optional(&locale.variant_code)?,
+    ])
+}
+
+unsafe extern "C" fn update_semantics(
+    update: *const FlutterSemanticsUpdate2,
+    user_data: *mut c_void,
+) {
+    catch_panic("update_semantics_callback", || {
+        let engine = user_data.cast::<FlutterEngineInner>().as_ref().unwrap();
+        let update = SemanticsUpdate::from_raw(update.as_ref().unwrap());
 
-    true
+        let removed = engine.semantics_tree.borrow_mut().apply(update.clone());
+
+        if let Some(handler) = &engine.semantics_handler {
+            handler.on_update(&engine.semantics_tree.borrow(), &update, &removed);
+        }
+    });
+}
+
+/// The locales last passed to [FlutterEngine::update_locales]. These are global since the engine
+/// doesn't pass user data to [compute_platform_resolved_locale].
+static PREFERRED_LOCALES: Mutex<Vec<Locale>> = Mutex::new(Vec::new());
+
+unsafe extern "C" fn compute_platform_resolved_locale(
+    supported_locales: *mut *const FlutterLocale,
+    count: usize,
+) -> *const FlutterLocale {
+    catch_panic("compute_platform_resolved_locale_callback", || {
+        if count == 0 {
+            return ptr::null();
+        }
+
+        let supported_locales = std::slice::from_raw_parts(supported_locales, count);
+
+        let optional = |code: *const c_char| {
+            (!code.is_null()).then(|| CStr::from_ptr(code).to_string_lossy().into_owned())
+        };
+
+        let supported = supported_locales
+            .iter()
+            .map(|&locale| {
+                let locale = &*locale;
+                Locale {
+                    language_code: optional(locale.language_code).unwrap_or_default(),
+                    country_code: optional(locale.country_code),
+                    script_code: optional(locale.script_code),
+                    variant_code: optional(locale.variant_code),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        match locale::resolve_locale(&PREFERRED_LOCALES.lock(), &supported) {
+            Some(i) => supported_locales[i],
+            None => ptr::null(),
+        }
+    })
+    .unwrap_or(ptr::null())
 }
 
 unsafe extern "C" fn log_message(tag: *const c_char, message: *const c_char, _: *mut c_void) {
-    let tag = CStr::from_ptr(tag).to_string_lossy();
-    let message = CStr::from_ptr(message).to_string_lossy();
-    eprintln!("{tag}: {message}");
+    catch_panic("log_message_callback", || {
+        let tag = CStr::from_ptr(tag).to_string_lossy();
+        let message = CStr::from_ptr(message).to_string_lossy();
+        eprintln!("{tag}: {message}");
+    });
 }
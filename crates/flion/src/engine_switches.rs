@@ -0,0 +1,78 @@
+use std::env;
+
+/// The environment variable that engine switches are read from in debug builds, separated by
+/// whitespace. These are passed after the switches set with [EngineSwitches].
+pub const ENGINE_SWITCHES_VAR: &str = "FLION_ENGINE_SWITCHES";
+
+/// Command line switches passed to the engine, such as `--verbose-logging`.
+#[derive(Clone, Debug, Default)]
+pub struct EngineSwitches {
+    switches: Vec<String>,
+    dart_flags: Vec<String>,
+}
+
+impl EngineSwitches {
+    pub fn new() -> EngineSwitches {
+        EngineSwitches::default()
+    }
+
+    /// Adds a switch, including the leading `--`.
+    pub fn switch(mut self, switch: impl Into<String>) -> Self {
+        self.switches.push(switch.into());
+        self
+    }
+
+    /// Logs verbose engine messages.
+    pub fn verbose_logging(self) -> Self {
+        self.switch("--verbose-logging")
+    }
+
+    /// Enables or disables the Impeller renderer.
+    pub fn impeller(self, enabled: bool) -> Self {
+        self.switch(format!("--enable-impeller={enabled}"))
+    }
+
+    /// Records a timeline of engine startup, which is written to `start_up_info.json`.
+    pub fn trace_startup(self) -> Self {
+        self.switch("--trace-startup")
+    }
+
+    /// Sets the port that the Dart VM service listens on. A port of 0 picks a free port. The VM
+    /// service is only available in debug and profile builds of the engine.
+    pub fn vm_service_port(self, port: u16) -> Self {
+        self.switch(format!("--vm-service-port={port}"))
+    }
+
+    /// Adds flags that are passed to the Dart VM, such as `--no-enable-asserts`. Release builds of
+    /// the engine only accept a small set of flags.
+    pub fn dart_flags<I>(mut self, flags: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.dart_flags.extend(flags.into_iter().map(Into::into));
+        self
+    }
+
+    /// Returns the switches as passed to the engine, including the program name and any switches
+    /// from [ENGINE_SWITCHES_VAR].
+    pub(crate) fn to_args(&self) -> Vec<String> {
+        let mut args = vec!["flion".to_owned()];
+
+        args.extend(self.switches.iter().cloned());
+
+        if !self.dart_flags.is_empty() {
+            args.push(format!("--dart-flags={}", self.dart_flags.join(",")));
+        }
+
+        // Overrides are intended for development, so they can't be used to change the behaviour
+        // of release builds.
+        if cfg!(debug_assertions)
+            && let Ok(overrides) = env::var(ENGINE_SWITCHES_VAR)
+        {
+            args.extend(overrides.split_whitespace().map(str::to_owned));
+        }
+
+        args
+    }
+}
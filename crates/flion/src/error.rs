@@ -0,0 +1,158 @@
+use std::error::Error as StdError;
+use std::ffi::NulError;
+use std::fmt::{self, Display};
+use std::io;
+use std::path::PathBuf;
+
+use flutter_embedder::{
+    FlutterEngineResult, FlutterEngineResult_kInternalInconsistency,
+    FlutterEngineResult_kInvalidArguments, FlutterEngineResult_kInvalidLibraryVersion,
+    FlutterEngineResult_kSuccess,
+};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// The errors returned by flion.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The engine failed to initialize or start running.
+    EngineInit(FlutterEngineResult),
+    /// The `flutter_assets` directory was not found at the given path.
+    MissingAssets(PathBuf),
+    /// The engine runs AOT compiled Dart code, but no AOT library was found. The path is `None`
+    /// if no path was provided.
+    MissingAotData(Option<PathBuf>),
+    /// The AOT library at the given path could not be loaded by the engine.
+    InvalidAotData {
+        path: PathBuf,
+        result: FlutterEngineResult,
+    },
+    /// A path passed to the engine was not valid unicode or contained a nul byte.
+    InvalidPath(PathBuf),
+    /// A message was sent after the engine has shut down.
+    MessengerNotRunning,
+    /// A call to the embedder API failed.
+    Engine {
+        function: &'static str,
+        result: FlutterEngineResult,
+    },
+    /// A channel name contained a nul byte.
+    InvalidChannelName(NulError),
+    /// The Dart entrypoint or one of its arguments contained a nul byte.
+    InvalidDartArgs(NulError),
+    /// An engine switch contained a nul byte.
+    InvalidEngineSwitches(NulError),
+    /// A message could not be encoded or decoded.
+    Codec(BoxError),
+    Io(io::Error),
+    /// Setting up the window, graphics devices or event loop failed, or a platform view returned
+    /// an error.
+    Backend(BoxError),
+}
+
+impl Error {
+    pub(crate) fn codec(e: impl Into<BoxError>) -> Error {
+        Error::Codec(e.into())
+    }
+
+    /// Wraps an error from the platform or from application code, such as a platform view, as an
+    /// [Error::Backend].
+    pub fn backend(e: impl Into<BoxError>) -> Error {
+        Error::Backend(e.into())
+    }
+
+    /// Returns an [Error::Engine] if `result` is not a success.
+    pub(crate) fn check(function: &'static str, result: FlutterEngineResult) -> Result<()> {
+        if result == FlutterEngineResult_kSuccess {
+            Ok(())
+        } else {
+            Err(Error::Engine { function, result })
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::EngineInit(result) => {
+                write!(
+                    f,
+                    "failed to start the flutter engine: {}",
+                    ResultName(*result)
+                )
+            }
+            Error::MissingAssets(path) => write!(f, "flutter assets not found at {path:?}"),
+            Error::MissingAotData(None) => write!(f, "no AOT library path was provided"),
+            Error::MissingAotData(Some(path)) => write!(f, "AOT library not found at {path:?}"),
+            Error::InvalidAotData { path, result } => {
+                write!(
+                    f,
+                    "failed to load AOT data from {path:?}: {}",
+                    ResultName(*result)
+                )
+            }
+            Error::InvalidPath(path) => write!(f, "invalid path {path:?}"),
+            Error::MessengerNotRunning => write!(f, "engine is not running"),
+            Error::Engine { function, result } => {
+                write!(f, "{function} failed: {}", ResultName(*result))
+            }
+            Error::InvalidChannelName(e) => write!(f, "invalid channel name: {e}"),
+            Error::InvalidDartArgs(e) => write!(f, "invalid dart entrypoint arguments: {e}"),
+            Error::InvalidEngineSwitches(e) => write!(f, "invalid engine switches: {e}"),
+            Error::Codec(e) => write!(f, "codec error: {e}"),
+            Error::Io(e) => write!(f, "{e}"),
+            Error::Backend(e) => write!(f, "backend error: {e}"),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::InvalidChannelName(e)
+            | Error::InvalidDartArgs(e)
+            | Error::InvalidEngineSwitches(e) => Some(e),
+            Error::Codec(e) | Error::Backend(e) => Some(&**e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::codec(e)
+    }
+}
+
+impl From<crate::codec::Error> for Error {
+    fn from(e: crate::codec::Error) -> Self {
+        Error::codec(e)
+    }
+}
+
+impl From<windows::core::Error> for Error {
+    fn from(e: windows::core::Error) -> Self {
+        Error::backend(e)
+    }
+}
+
+struct ResultName(FlutterEngineResult);
+
+impl Display for ResultName {
+    #[expect(non_upper_case_globals)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self.0 {
+            FlutterEngineResult_kSuccess => "success",
+            FlutterEngineResult_kInvalidLibraryVersion => "invalid library version",
+            FlutterEngineResult_kInvalidArguments => "invalid arguments",
+            FlutterEngineResult_kInternalInconsistency => "internal inconsistency",
+            _ => "unknown error",
+        };
+
+        write!(f, "{name} ({})", self.0)
+    }
+}
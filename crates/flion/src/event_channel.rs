@@ -0,0 +1,183 @@
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::codec::{CodecExtension, DecodeLimits, EncodableValue};
+use crate::engine::ThreadSafeMessenger;
+use crate::error::Error;
+use crate::standard_method_channel::{
+    StandardMethodCodec, StandardMethodError, StandardMethodHandler, StandardMethodReply,
+};
+
+/// Handles a Dart stream listening to an [EventChannel], equivalent to `StreamHandler` in the
+/// platform APIs of other embedders.
+pub trait StreamHandler {
+    /// Called when Dart starts listening to the stream. Events should be sent to `sink` until
+    /// [StreamHandler::on_cancel] is called.
+    fn on_listen(&self, args: EncodableValue, sink: EventSink) -> Result<(), StandardMethodError>;
+
+    /// Called when Dart stops listening to the stream.
+    fn on_cancel(&self, args: EncodableValue) -> Result<(), StandardMethodError>;
+}
+
+/// Streams events to an `EventChannel` on the Dart side. This must be registered as the handler
+/// for its channel, so that it can receive `listen` and `cancel` calls from Dart.
+pub struct EventChannel<H> {
+    messenger: ThreadSafeMessenger,
+    name: Arc<str>,
+    codec: StandardMethodCodec,
+    handler: H,
+    active_sink: RefCell<Option<Arc<AtomicBool>>>,
+}
+
+impl<H: StreamHandler> EventChannel<H> {
+    pub fn new(
+        messenger: ThreadSafeMessenger,
+        name: &str,
+        codec: StandardMethodCodec,
+        handler: H,
+    ) -> crate::Result<EventChannel<H>> {
+        // Sinks send messages asynchronously, so an invalid name is reported here instead.
+        CString::from_str(name).map_err(Error::InvalidChannelName)?;
+
+        Ok(EventChannel {
+            messenger,
+            name: Arc::from(name),
+            codec,
+            handler,
+            active_sink: RefCell::new(None),
+        })
+    }
+
+    /// Returns true if Dart is currently listening to the stream.
+    pub fn has_listener(&self) -> bool {
+        self.active_sink.borrow().is_some()
+    }
+
+    fn listen(&self, args: EncodableValue, reply: StandardMethodReply) {
+        // Dart may listen again without cancelling first, e.g. after a hot restart. The previous
+        // listener is cancelled so that the handler can clean up.
+        if let Some(active) = self.active_sink.take() {
+            active.store(false, Ordering::Release);
+            if let Err(e) = self.handler.on_cancel(EncodableValue::Null) {
+                tracing::error!(channel = ?self.name, "failed to cancel previous listener: {e}");
+            }
+        }
+
+        let active = Arc::new(AtomicBool::new(true));
+        let sink = EventSink {
+            messenger: self.messenger.clone(),
+            name: self.name.clone(),
+            codec: self.codec,
+            active: active.clone(),
+        };
+
+        match self.handler.on_listen(args, sink) {
+            Ok(()) => {
+                *self.active_sink.borrow_mut() = Some(active);
+                reply.success(&EncodableValue::Null);
+            }
+            Err(e) => {
+                active.store(false, Ordering::Release);
+                reply.error(&e.code, e.message.as_deref(), &e.details);
+            }
+        }
+    }
+
+    fn cancel(&self, args: EncodableValue, reply: StandardMethodReply) {
+        let Some(active) = self.active_sink.take() else {
+            reply.error(
+                "error",
+                Some("No active stream to cancel"),
+                &EncodableValue::Null,
+            );
+            return;
+        };
+
+        active.store(false, Ordering::Release);
+
+        match self.handler.on_cancel(args) {
+            Ok(()) => reply.success(&EncodableValue::Null),
+            Err(e) => reply.error(&e.code, e.message.as_deref(), &e.details),
+        }
+    }
+}
+
+impl<H: StreamHandler> StandardMethodHandler for EventChannel<H> {
+    fn handle(&self, method: &str, args: EncodableValue, reply: StandardMethodReply) {
+        match method {
+            "listen" => self.listen(args, reply),
+            "cancel" => self.cancel(args, reply),
+            _ => {
+                tracing::warn!(method, "unimplemented");
+                reply.not_implemented();
+            }
+        }
+    }
+
+    fn codec_extension(&self) -> &'static dyn CodecExtension {
+        self.codec.extension()
+    }
+
+    fn decode_limits(&self) -> DecodeLimits {
+        self.codec.limits()
+    }
+}
+
+/// Sends events to the Dart stream that is listening to an [EventChannel]. Sinks can be cloned
+/// and used from any thread, and events are sent on the platform thread in the order that they
+/// were sent. Once the stream is cancelled or ended, sending does nothing.
+#[derive(Clone)]
+pub struct EventSink {
+    messenger: ThreadSafeMessenger,
+    name: Arc<str>,
+    codec: StandardMethodCodec,
+    active: Arc<AtomicBool>,
+}
+
+impl EventSink {
+    /// Returns false once Dart has stopped listening, or the stream has been ended.
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Acquire)
+    }
+
+    pub fn success(&self, event: &EncodableValue) -> crate::Result<()> {
+        if !self.is_active() {
+            return Ok(());
+        }
+
+        let message = self
+            .codec
+            .encode_success_envelope(event)
+            .map_err(Error::codec)?;
+        self.messenger.send_platform_message(&self.name, message)
+    }
+
+    pub fn error(
+        &self,
+        code: &str,
+        message: Option<&str>,
+        details: &EncodableValue,
+    ) -> crate::Result<()> {
+        if !self.is_active() {
+            return Ok(());
+        }
+
+        let message = self
+            .codec
+            .encode_error_envelope(code, message, details)
+            .map_err(Error::codec)?;
+        self.messenger.send_platform_message(&self.name, message)
+    }
+
+    /// Closes the stream on the Dart side. No more events can be sent after this.
+    pub fn end_of_stream(self) -> crate::Result<()> {
+        if !self.active.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
+
+        self.messenger.send_platform_message(&self.name, vec![])
+    }
+}
@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::engine::{BinaryMessageHandler, BinaryMessageReply};
+
+/// Encoding for method calls and replies on channels that use `JSONMethodCodec` on the Dart side.
+pub struct JsonMethodCodec;
+
+#[derive(Debug, Deserialize)]
+pub struct JsonMethodCall {
+    pub method: String,
+    #[serde(default)]
+    pub args: Value,
+}
+
+impl JsonMethodCodec {
+    pub fn encode_method_call(method: &str, args: &impl Serialize) -> serde_json::Result<Vec<u8>> {
+        #[derive(Serialize)]
+        struct MethodCall<'a, T> {
+            method: &'a str,
+            args: &'a T,
+        }
+
+        serde_json::to_vec(&MethodCall { method, args })
+    }
+
+    pub fn decode_method_call(message: &[u8]) -> serde_json::Result<JsonMethodCall> {
+        serde_json::from_slice(message)
+    }
+
+    pub fn encode_success_envelope(result: &impl Serialize) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec(&[result])
+    }
+
+    pub fn encode_error_envelope(
+        code: &str,
+        message: Option<&str>,
+        details: &impl Serialize,
+    ) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec(&(code, message, details))
+    }
+}
+
+pub trait JsonMethodHandler {
+    fn handle(&self, method: &str, args: Value, reply: JsonMethodReply);
+}
+
+/// Adapts a [JsonMethodHandler] so that it can be registered as a platform message handler.
+pub struct JsonMethodChannel<T>(T);
+
+impl<T: JsonMethodHandler> JsonMethodChannel<T> {
+    pub fn new(handler: T) -> JsonMethodChannel<T> {
+        JsonMethodChannel(handler)
+    }
+}
+
+impl<T: JsonMethodHandler> BinaryMessageHandler for JsonMethodChannel<T> {
+    fn handle(&self, message: &[u8], reply: BinaryMessageReply) {
+        let reply = JsonMethodReply(reply);
+
+        let call = match JsonMethodCodec::decode_method_call(message) {
+            Ok(call) => call,
+            Err(e) => {
+                tracing::error!("invalid method call: {e}");
+                reply.not_implemented();
+                return;
+            }
+        };
+
+        self.0.handle(&call.method, call.args, reply);
+    }
+
+    fn panic_reply(&self, message: &str) -> Vec<u8> {
+        JsonMethodCodec::encode_error_envelope("panic", Some(message), &()).unwrap_or_default()
+    }
+}
+
+pub struct JsonMethodReply(BinaryMessageReply);
+
+impl JsonMethodReply {
+    pub fn success(self, value: &impl Serialize) {
+        match JsonMethodCodec::encode_success_envelope(value) {
+            Ok(bytes) => self.0.send(&bytes),
+            Err(e) => self.error("encode_error", Some(&e.to_string()), &()),
+        }
+    }
+
+    pub fn error(self, code: &str, message: Option<&str>, details: &impl Serialize) {
+        let bytes = match JsonMethodCodec::encode_error_envelope(code, message, details) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                tracing::error!("failed to encode error details: {e}");
+                JsonMethodCodec::encode_error_envelope(code, message, &()).unwrap_or_else(|e| {
+                    tracing::error!("failed to encode error: {e}");
+                    vec![]
+                })
+            }
+        };
+        self.0.send(&bytes);
+    }
+
+    pub fn not_implemented(self) {
+        self.0.not_implemented();
+    }
+}
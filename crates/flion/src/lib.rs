@@ -3,10 +3,14 @@
 mod compositor;
 mod egl;
 mod engine;
+mod engine_switches;
+mod error;
 mod error_utils;
 mod keyboard;
 mod keymap;
+mod locale;
 mod mouse_cursor;
+mod panic_guard;
 mod platform_views;
 mod plugins_shim;
 mod settings;
@@ -15,10 +19,16 @@ mod text_input;
 mod views;
 mod window;
 
+pub mod basic_message_channel;
 pub mod codec;
+pub mod event_channel;
+pub mod json_method_channel;
+pub mod method_channel_router;
+pub mod replay;
+pub mod semantics;
 pub mod standard_method_channel;
 
-use std::cell::RefCell;
+use std::cell::{Ref, RefCell};
 use std::collections::HashMap;
 use std::ffi::c_void;
 use std::path::{Path, PathBuf};
@@ -56,11 +66,23 @@ use crate::compositor::FlutterCompositor;
 use crate::egl::EglDevice;
 use crate::engine::{FlutterEngine, FlutterEngineConfig, PointerPhase};
 use crate::error_utils::ResultExt;
+use crate::json_method_channel::JsonMethodChannel;
 use crate::keyboard::Keyboard;
+use crate::locale::LocaleChangeListener;
+use crate::method_channel_router::MethodChannelRouter;
 use crate::mouse_cursor::MouseCursorHandler;
+use crate::semantics::{SemanticsActions, SemanticsHandler, SemanticsTree};
 use crate::text_input::{TextInputHandler, TextInputState};
 
-pub use crate::engine::{BinaryMessageHandler, BinaryMessageReply, BinaryMessenger};
+pub use crate::engine::{
+    BinaryMessageHandler, BinaryMessageReply, BinaryMessenger, PayloadCodec,
+    PlatformMessageHandlers, ThreadSafeMessenger, TrafficDirection, TrafficInspector,
+    TrafficRecord, TrafficReply,
+};
+pub use crate::engine_switches::{EngineSwitches, ENGINE_SWITCHES_VAR};
+pub use crate::error::{Error, Result};
+pub use crate::locale::{resolve_locale, Locale, LocaleProvider, SystemLocaleProvider};
+pub use crate::panic_guard::CallbackPanic;
 pub use crate::platform_views::{CompositorContext, PlatformView, PlatformViewUpdateArgs};
 
 #[doc(hidden)]
@@ -81,6 +103,14 @@ pub struct FlionAppBuilder<'a> {
     bundle_path: PathBuf,
     platform_message_handlers: Vec<(&'a str, Box<dyn BinaryMessageHandler>)>,
     platform_view_factories: HashMap<String, Box<dyn PlatformViewFactory>>,
+    panic_hook: Option<Arc<dyn Fn(&CallbackPanic) + Send + Sync>>,
+    traffic_inspector: Option<TrafficInspector>,
+    channel_buffer_size: usize,
+    dart_entrypoint: Option<String>,
+    dart_args: Vec<String>,
+    engine_switches: EngineSwitches,
+    locale_provider: Rc<dyn LocaleProvider>,
+    semantics_handler: Option<Box<dyn SemanticsHandler>>,
 }
 
 impl<'a> FlionAppBuilder<'a> {
@@ -97,6 +127,17 @@ impl<'a> FlionAppBuilder<'a> {
             bundle_path,
             platform_message_handlers: vec![],
             platform_view_factories: HashMap::new(),
+            panic_hook: None,
+            traffic_inspector: None,
+            channel_buffer_size: 0,
+            dart_entrypoint: None,
+            dart_args: env::args_os()
+                .skip(1)
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            engine_switches: EngineSwitches::new(),
+            locale_provider: Rc::new(SystemLocaleProvider),
+            semantics_handler: None,
         }
     }
 
@@ -114,6 +155,10 @@ impl<'a> FlionAppBuilder<'a> {
         self
     }
 
+    pub fn with_method_channel(self, name: &'a str, router: MethodChannelRouter) -> Self {
+        self.with_platform_message_handler(name, Box::new(router))
+    }
+
     pub fn with_platform_view_factory(
         mut self,
         name: &'a str,
@@ -124,7 +169,73 @@ impl<'a> FlionAppBuilder<'a> {
         self
     }
 
-    pub fn build(self) -> eyre::Result<FlionApp> {
+    /// Sets a hook that is called when a panic is caught in a callback from the engine, such as a
+    /// platform message handler. Panics are always logged, and don't abort the app.
+    pub fn with_panic_hook(
+        mut self,
+        hook: impl Fn(&CallbackPanic) + Send + Sync + 'static,
+    ) -> Self {
+        self.panic_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Records the platform messages exchanged with Dart. They can be queried with
+    /// [FlionApp::traffic_inspector].
+    pub fn with_traffic_inspector(mut self, inspector: TrafficInspector) -> Self {
+        self.traffic_inspector = Some(inspector);
+        self
+    }
+
+    /// Sets the number of messages from Dart that are buffered for each channel until a handler is
+    /// set for it. This is 0 by default, so messages on channels without a handler are replied to
+    /// as not implemented, which Dart reports as a `MissingPluginException`. Buffering can also be
+    /// enabled for individual channels with [FlionApp::resize_channel_buffer].
+    pub fn with_channel_buffer_size(mut self, size: usize) -> Self {
+        self.channel_buffer_size = size;
+        self
+    }
+
+    /// Runs the named Dart function instead of `main`. The function must be annotated with
+    /// `@pragma('vm:entry-point')` so that it isn't removed from AOT builds.
+    pub fn with_entrypoint(mut self, name: impl Into<String>) -> Self {
+        self.dart_entrypoint = Some(name.into());
+        self
+    }
+
+    /// Sets the arguments passed to the Dart entrypoint. By default, the process's command line
+    /// arguments are passed, excluding the executable name.
+    pub fn with_dart_args<I>(mut self, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.dart_args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the command line switches passed to the engine. In debug builds, additional switches
+    /// can be set with the [ENGINE_SWITCHES_VAR] environment variable.
+    pub fn with_engine_switches(mut self, switches: EngineSwitches) -> Self {
+        self.engine_switches = switches;
+        self
+    }
+
+    /// Sets the provider of the locales reported to Dart. By default, these are read from the
+    /// system with [SystemLocaleProvider].
+    pub fn with_locale_provider(mut self, provider: impl LocaleProvider + 'static) -> Self {
+        self.locale_provider = Rc::new(provider);
+        self
+    }
+
+    /// Enables semantics, and sets a handler that receives the semantics tree whenever it changes.
+    pub fn with_semantics_handler(mut self, handler: impl SemanticsHandler + 'static) -> Self {
+        self.semantics_handler = Some(Box::new(handler));
+        self
+    }
+
+    pub fn build(self) -> Result<FlionApp> {
+        panic_guard::set_panic_hook(self.panic_hook);
+
         let device = unsafe {
             let mut device = Default::default();
 
@@ -140,15 +251,15 @@ impl<'a> FlionAppBuilder<'a> {
                 None,
             )?;
 
-            device.ok_or_eyre("failed to create D3D11 device")?
+            device.ok_or_else(|| Error::backend("failed to create D3D11 device"))?
         };
 
         let composition_device: IDCompositionDevice =
             unsafe { DCompositionCreateDevice2(&device.cast::<IDXGIDevice>()?)? };
 
-        let egl = EglDevice::create(&device)?;
+        let egl = EglDevice::create(&device).map_err(Error::backend)?;
 
-        let task_executor = Rc::new(FlutterTaskExecutor::new()?);
+        let task_executor = Rc::new(FlutterTaskExecutor::new().map_err(Error::backend)?);
         let task_queue = task_executor.queue().clone();
 
         let view_manager = Arc::new(Mutex::new(ViewManager::new()));
@@ -162,7 +273,8 @@ impl<'a> FlionAppBuilder<'a> {
                 view_manager: view_manager.clone(),
                 platform_task_queue: task_queue.clone(),
             }),
-        )?;
+        )
+        .map_err(Error::backend)?;
 
         let platform_views = compositor.platform_views();
 
@@ -178,39 +290,61 @@ impl<'a> FlionAppBuilder<'a> {
 
         platform_message_handlers.extend(self.platform_message_handlers);
 
-        // TODO: Disable environment variable lookup in release builds.
         // These variables are provided by the flion cli during development, and are not intended
         // to be used in release builds.
-        let assets_path = env::var("FLION_ASSETS_PATH")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| self.bundle_path.join("flutter_assets"));
+        let dev_path = |name: &str| {
+            cfg!(debug_assertions)
+                .then(|| env::var_os(name))
+                .flatten()
+                .map(PathBuf::from)
+        };
+
+        let assets_path = dev_path("FLION_ASSETS_PATH")
+            .unwrap_or_else(|| self.bundle_path.join("flutter_assets"));
 
-        let aot_library_path = env::var("FLION_AOT_LIBRARY_PATH")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| self.bundle_path.join("app.so"));
+        let aot_library_path =
+            dev_path("FLION_AOT_LIBRARY_PATH").unwrap_or_else(|| self.bundle_path.join("app.so"));
+
+        let command_line_args = self.engine_switches.to_args();
+        let semantics_enabled = self.semantics_handler.is_some();
 
         let engine = Rc::new(FlutterEngine::new(FlutterEngineConfig {
-            assets_path: assets_path.to_str().ok_or_eyre("invalid assets path")?,
+            assets_path: assets_path
+                .to_str()
+                .ok_or_else(|| Error::InvalidPath(assets_path.clone()))?,
             aot_library_path: Some(
                 aot_library_path
                     .to_str()
-                    .ok_or_eyre("invalid aot library path")?,
+                    .ok_or_else(|| Error::InvalidPath(aot_library_path.clone()))?,
             ),
+            dart_entrypoint: self.dart_entrypoint.as_deref(),
+            dart_entrypoint_args: &self.dart_args,
+            command_line_args: &command_line_args,
             egl: egl.clone(),
             compositor,
             platform_task_handler: Box::new(move |task| task_queue.enqueue(task)),
             platform_message_handlers,
+            traffic_inspector: self.traffic_inspector.map(Arc::new),
+            channel_buffer_size: self.channel_buffer_size,
+            semantics_handler: self.semantics_handler,
         })?);
 
         task_executor.init(engine.clone());
 
         settings::send_to_engine(&engine)?;
 
+        engine.update_locales(&self.locale_provider.preferred_locales())?;
+
+        if semantics_enabled {
+            engine.update_semantics_enabled(true)?;
+        }
+
         Ok(FlionApp {
             engine,
             composition_device,
             view_manager,
             task_executor,
+            locale_provider: self.locale_provider,
         })
     }
 }
@@ -220,6 +354,7 @@ pub struct FlionApp {
     composition_device: IDCompositionDevice,
     view_manager: Arc<Mutex<ViewManager>>,
     task_executor: Rc<FlutterTaskExecutor>,
+    locale_provider: Rc<dyn LocaleProvider>,
 }
 
 impl FlionApp {
@@ -231,25 +366,85 @@ impl FlionApp {
         self.engine.messenger()
     }
 
+    /// Returns a messenger that can be used from any thread.
+    pub fn thread_safe_messenger(&self) -> ThreadSafeMessenger {
+        ThreadSafeMessenger::new(self.engine.messenger(), self.task_executor.queue().clone())
+    }
+
+    /// Sets the handler for a channel, returning the previous handler if there was one. The channel
+    /// may be a wildcard pattern such as `myplugin/player/*`, see [PlatformMessageHandlers].
     pub fn set_platform_message_handler(
         &self,
         name: impl Into<String>,
         handler: impl BinaryMessageHandler + 'static,
-    ) {
+    ) -> Option<Rc<dyn BinaryMessageHandler>> {
         self.engine.set_platform_message_handler(name, handler)
     }
 
-    pub fn run_event_loop(self) -> eyre::Result<()> {
-        let event_loop = EventLoopBuilder::new().build()?;
+    /// Removes the handler for a channel, returning it if there was one.
+    pub fn remove_platform_message_handler(
+        &self,
+        name: &str,
+    ) -> Option<Rc<dyn BinaryMessageHandler>> {
+        self.engine.remove_platform_message_handler(name)
+    }
+
+    /// Sets the number of messages from Dart that are buffered for `channel` until a handler is set
+    /// for it. If more messages are already buffered, the oldest ones are dropped.
+    pub fn resize_channel_buffer(&self, channel: &str, size: usize) {
+        self.engine.resize_channel_buffer(channel, size)
+    }
+
+    /// Sets whether dropping messages on `channel` when its buffer is full is expected. If not, a
+    /// warning is logged for each dropped message.
+    pub fn allow_channel_buffer_overflow(&self, channel: &str, allowed: bool) {
+        self.engine.allow_channel_buffer_overflow(channel, allowed)
+    }
+
+    /// Returns the inspector set with [FlionAppBuilder::with_traffic_inspector], if any.
+    pub fn traffic_inspector(&self) -> Option<&TrafficInspector> {
+        self.engine.traffic_inspector()
+    }
+
+    /// Enables or disables semantics. They are enabled on startup if a handler was set with
+    /// [FlionAppBuilder::with_semantics_handler].
+    pub fn set_semantics_enabled(&self, enabled: bool) -> Result<()> {
+        self.engine.update_semantics_enabled(enabled)
+    }
+
+    /// Returns the current semantics tree, which is empty while semantics are disabled.
+    pub fn semantics_tree(&self) -> Ref<'_, SemanticsTree> {
+        self.engine.semantics_tree()
+    }
+
+    /// Performs a semantics action on a node, for example when requested by a screen reader.
+    /// `args` are the action's arguments encoded with the standard message codec, or empty if it
+    /// has none.
+    pub fn dispatch_semantics_action(
+        &self,
+        node_id: i32,
+        action: SemanticsActions,
+        args: &[u8],
+    ) -> Result<()> {
+        self.engine.dispatch_semantics_action(node_id, action, args)
+    }
+
+    pub fn run_event_loop(self) -> Result<()> {
+        let event_loop = EventLoopBuilder::new().build().map_err(Error::backend)?;
 
         let parent_window = WindowBuilder::new()
             .with_inner_size(LogicalSize::new(1280, 720))
             .with_no_redirection_bitmap(true)
-            .build(&event_loop)?;
+            .build(&event_loop)
+            .map_err(Error::backend)?;
 
         let parent_window = Rc::new(parent_window);
 
-        let parent_hwnd = match parent_window.window_handle()?.as_raw() {
+        let parent_hwnd = match parent_window
+            .window_handle()
+            .map_err(Error::backend)?
+            .as_raw()
+        {
             RawWindowHandle::Win32(handle) => HWND(handle.hwnd.get() as _),
             _ => unreachable!(),
         };
@@ -264,22 +459,36 @@ impl FlionApp {
             )?;
         }
 
+        // The listener is kept alive until the event loop exits.
+        let _locale_listener = LocaleChangeListener::new(parent_hwnd, {
+            let engine = self.engine.clone();
+            let locale_provider = self.locale_provider.clone();
+            move || {
+                let _ = engine
+                    .update_locales(&locale_provider.preferred_locales())
+                    .trace_err();
+            }
+        });
+
         let root_visual = unsafe { self.composition_device.CreateVisual()? };
         self.view_manager.lock().insert(0, root_visual.clone());
 
         let text_input = Rc::new(RefCell::new(TextInputState::new()));
 
         let size = parent_window.inner_size();
-        let window = Rc::new(Window::new(
-            size.width,
-            size.height,
-            Box::new(FlutterWindowHandler {
-                engine: self.engine.clone(),
-                task_executor: self.task_executor.clone(),
-                view_manager: self.view_manager.clone(),
-                keyboard: Keyboard::new(self.engine.clone(), text_input.clone()),
-            }),
-        )?);
+        let window = Rc::new(
+            Window::new(
+                size.width,
+                size.height,
+                Box::new(FlutterWindowHandler {
+                    engine: self.engine.clone(),
+                    task_executor: self.task_executor.clone(),
+                    view_manager: self.view_manager.clone(),
+                    keyboard: Keyboard::new(self.engine.clone(), text_input.clone()),
+                }),
+            )
+            .map_err(Error::backend)?,
+        );
 
         window.set_parent(parent_hwnd);
         window.request_focus();
@@ -294,7 +503,7 @@ impl FlionApp {
 
         self.engine.set_platform_message_handler(
             "flutter/textinput",
-            TextInputHandler::new(text_input.clone()),
+            JsonMethodChannel::new(TextInputHandler::new(text_input.clone())),
         );
 
         self.engine.set_platform_message_handler(
@@ -302,10 +511,10 @@ impl FlionApp {
             MouseCursorHandler::new(Rc::downgrade(&window)),
         );
 
-        let mut plugins_engine = Box::new(FlutterPluginsEngine::new(
-            self.engine.clone(),
-            window.window_handle(),
-        )?);
+        let mut plugins_engine = Box::new(
+            FlutterPluginsEngine::new(self.engine.clone(), window.window_handle())
+                .map_err(Error::backend)?,
+        );
 
         for init in PLUGINS {
             unsafe {
@@ -322,25 +531,27 @@ impl FlionApp {
 
         unsafe { composition_target.SetRoot(&root_visual)? };
 
-        event_loop.run(move |event, target| match event {
-            winit::event::Event::WindowEvent { window_id, event }
-                if window_id == parent_window.id() =>
-            {
-                match event {
-                    winit::event::WindowEvent::CloseRequested => target.exit(),
+        event_loop
+            .run(move |event, target| match event {
+                winit::event::Event::WindowEvent { window_id, event }
+                    if window_id == parent_window.id() =>
+                {
+                    match event {
+                        winit::event::WindowEvent::CloseRequested => target.exit(),
 
-                    winit::event::WindowEvent::Focused(true) => window.request_focus(),
+                        winit::event::WindowEvent::Focused(true) => window.request_focus(),
 
-                    winit::event::WindowEvent::Resized(PhysicalSize { width, height }) => {
-                        window.set_position_and_size(0, 0, width, height)
-                    }
+                        winit::event::WindowEvent::Resized(PhysicalSize { width, height }) => {
+                            window.set_position_and_size(0, 0, width, height)
+                        }
 
-                    _ => {}
+                        _ => {}
+                    }
                 }
-            }
 
-            _ => {}
-        })?;
+                _ => {}
+            })
+            .map_err(Error::backend)?;
 
         Ok(())
     }
@@ -0,0 +1,252 @@
+use std::cmp::Reverse;
+use std::env;
+use std::fmt::{self, Display};
+
+use windows::core::{PCWSTR, PWSTR};
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::Globalization::{GetUserPreferredUILanguages, MUI_LANGUAGE_NAME};
+use windows::Win32::UI::Shell::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass};
+use windows::Win32::UI::WindowsAndMessaging::WM_SETTINGCHANGE;
+
+use crate::panic_guard::catch_panic;
+
+/// A locale, like `Locale` in Dart.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Locale {
+    pub language_code: String,
+    pub country_code: Option<String>,
+    pub script_code: Option<String>,
+    pub variant_code: Option<String>,
+}
+
+impl Locale {
+    pub fn new(language_code: impl Into<String>) -> Locale {
+        Locale {
+            language_code: language_code.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_country(mut self, country_code: impl Into<String>) -> Self {
+        self.country_code = Some(country_code.into());
+        self
+    }
+
+    pub fn with_script(mut self, script_code: impl Into<String>) -> Self {
+        self.script_code = Some(script_code.into());
+        self
+    }
+
+    /// Parses a BCP 47 language tag such as `zh-Hant-TW`, or a POSIX locale name such as
+    /// `en_GB.UTF-8`. Returns `None` for the `C` and `POSIX` locales.
+    pub fn parse(name: &str) -> Option<Locale> {
+        // Drop the encoding and modifier from POSIX names.
+        let name = name.split(['.', '@']).next()?;
+
+        let mut subtags = name.split(['-', '_']);
+        let language = subtags.next()?;
+
+        if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic())
+        {
+            return None;
+        }
+
+        let mut locale = Locale::new(language.to_ascii_lowercase());
+
+        for subtag in subtags {
+            let is_alphabetic = subtag.chars().all(|c| c.is_ascii_alphabetic());
+            let is_numeric = subtag.chars().all(|c| c.is_ascii_digit());
+
+            if subtag.len() == 4
+                && is_alphabetic
+                && locale.script_code.is_none()
+                && locale.country_code.is_none()
+            {
+                let (first, rest) = subtag.split_at(1);
+                locale.script_code = Some(first.to_ascii_uppercase() + &rest.to_ascii_lowercase());
+            } else if ((subtag.len() == 2 && is_alphabetic) || (subtag.len() == 3 && is_numeric))
+                && locale.country_code.is_none()
+            {
+                locale.country_code = Some(subtag.to_ascii_uppercase());
+            } else if !subtag.is_empty() {
+                locale.variant_code = Some(subtag.to_owned());
+                break;
+            }
+        }
+
+        Some(locale)
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.language_code)?;
+
+        for subtag in [&self.script_code, &self.country_code, &self.variant_code]
+            .into_iter()
+            .flatten()
+        {
+            write!(f, "-{subtag}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Provides the user's preferred locales, which are reported to the engine on startup and whenever
+/// the system locale settings change.
+pub trait LocaleProvider {
+    /// Returns the user's preferred locales, most preferred first.
+    fn preferred_locales(&self) -> Vec<Locale>;
+}
+
+/// Reads the preferred locales from the user's preferred UI languages. If they can't be read, the
+/// `LANGUAGE`, `LC_ALL`, `LC_MESSAGES` and `LANG` environment variables are used instead.
+///
+/// The environment variables aren't preferred, since shells such as Git Bash and MSYS set `LANG`
+/// regardless of the user's language settings.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemLocaleProvider;
+
+impl LocaleProvider for SystemLocaleProvider {
+    fn preferred_locales(&self) -> Vec<Locale> {
+        match user_preferred_ui_languages() {
+            Ok(locales) if !locales.is_empty() => return locales,
+            Ok(_) => {}
+            Err(e) => tracing::error!("failed to get preferred ui languages: {e}"),
+        }
+
+        env_locales()
+    }
+}
+
+fn env_locales() -> Vec<Locale> {
+    let var = |name| env::var(name).ok().filter(|value| !value.is_empty());
+
+    let Some(primary) = var("LC_ALL")
+        .or_else(|| var("LC_MESSAGES"))
+        .or_else(|| var("LANG"))
+        .and_then(|name| Locale::parse(&name))
+    else {
+        return vec![];
+    };
+
+    // Like gettext, the LANGUAGE list is ignored if the locale is C.
+    let mut locales: Vec<Locale> = var("LANGUAGE")
+        .iter()
+        .flat_map(|list| list.split(':'))
+        .filter_map(Locale::parse)
+        .collect();
+
+    if !locales.contains(&primary) {
+        locales.push(primary);
+    }
+
+    locales
+}
+
+fn user_preferred_ui_languages() -> windows::core::Result<Vec<Locale>> {
+    let mut count = 0;
+    let mut len = 0;
+    unsafe { GetUserPreferredUILanguages(MUI_LANGUAGE_NAME, &mut count, None, &mut len)? };
+
+    let mut buffer = vec![0u16; len as usize];
+    unsafe {
+        GetUserPreferredUILanguages(
+            MUI_LANGUAGE_NAME,
+            &mut count,
+            Some(PWSTR(buffer.as_mut_ptr())),
+            &mut len,
+        )?
+    };
+
+    // The buffer is a list of nul terminated names, ending with an empty name.
+    Ok(buffer
+        .split(|&c| c == 0)
+        .take_while(|name| !name.is_empty())
+        .filter_map(|name| Locale::parse(&String::from_utf16_lossy(name)))
+        .collect())
+}
+
+/// Returns the index of the locale in `supported` that best matches the first locale in
+/// `preferred` that any of them match, or the first supported locale if there are no matches.
+pub fn resolve_locale(preferred: &[Locale], supported: &[Locale]) -> Option<usize> {
+    let score = |preferred: &Locale, supported: &Locale| {
+        if preferred.language_code != supported.language_code {
+            return 0;
+        }
+
+        let mut score = 1;
+        if supported.country_code.is_some() && preferred.country_code == supported.country_code {
+            score += 2;
+        }
+        if supported.script_code.is_some() && preferred.script_code == supported.script_code {
+            score += 1;
+        }
+        score
+    };
+
+    for preferred in preferred {
+        let best = supported
+            .iter()
+            .enumerate()
+            .map(|(i, supported)| (score(preferred, supported), i))
+            .filter(|&(score, _)| score > 0)
+            // Prefer the earliest supported locale if there is a tie.
+            .min_by_key(|&(score, i)| (Reverse(score), i));
+
+        if let Some((_, i)) = best {
+            return Some(i);
+        }
+    }
+
+    (!supported.is_empty()).then_some(0)
+}
+
+/// Calls a callback when the user's locale settings change, until it is dropped.
+pub(crate) struct LocaleChangeListener {
+    hwnd: HWND,
+    callback: *mut Box<dyn Fn()>,
+}
+
+impl LocaleChangeListener {
+    /// Listens for changes using the messages sent to `hwnd`, which must be a top-level window.
+    pub fn new(hwnd: HWND, callback: impl Fn() + 'static) -> LocaleChangeListener {
+        let callback = Box::into_raw(Box::new(Box::new(callback) as Box<dyn Fn()>));
+
+        unsafe {
+            let _ = SetWindowSubclass(hwnd, Some(subclass_proc), 0, callback as usize);
+        }
+
+        LocaleChangeListener { hwnd, callback }
+    }
+}
+
+impl Drop for LocaleChangeListener {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = RemoveWindowSubclass(self.hwnd, Some(subclass_proc), 0);
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+unsafe extern "system" fn subclass_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _id: usize,
+    data: usize,
+) -> LRESULT {
+    // Locale changes are broadcast with the "intl" section name.
+    if msg == WM_SETTINGCHANGE
+        && lparam.0 != 0
+        && unsafe { PCWSTR(lparam.0 as *const u16).to_string() }.is_ok_and(|s| s == "intl")
+    {
+        let callback = unsafe { &*(data as *const Box<dyn Fn()>) };
+        catch_panic("locale_change", callback);
+    }
+
+    unsafe { DefSubclassProc(hwnd, msg, wparam, lparam) }
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::codec::{self, EncodableValue};
+use crate::standard_method_channel::{
+    StandardMethodError, StandardMethodHandler, StandardMethodReply,
+};
+
+type MethodHandler = Box<dyn Fn(EncodableValue, StandardMethodReply)>;
+
+/// Dispatches calls on a standard method channel to a handler registered for each method. Calls
+/// to methods without a handler are replied to as not implemented.
+#[derive(Default)]
+pub struct MethodChannelRouter {
+    methods: HashMap<String, MethodHandler>,
+}
+
+impl MethodChannelRouter {
+    pub fn new() -> MethodChannelRouter {
+        MethodChannelRouter::default()
+    }
+
+    /// Registers a handler for `method`. Its arguments are deserialized from the method call, and
+    /// its result is serialized into the reply. Errors are sent back to Dart as a
+    /// `PlatformException`.
+    pub fn with_method<A, R, E, F>(mut self, method: &str, handler: F) -> Self
+    where
+        A: DeserializeOwned,
+        R: Serialize,
+        E: Into<StandardMethodError>,
+        F: Fn(A) -> Result<R, E> + 'static,
+    {
+        let handler = move |args: EncodableValue, reply: StandardMethodReply| {
+            let args = match codec::from_value(args) {
+                Ok(args) => args,
+                Err(e) => {
+                    reply.error("invalid_args", Some(&e.to_string()), &EncodableValue::Null);
+                    return;
+                }
+            };
+
+            match handler(args).map_err(Into::into) {
+                Ok(res) => match codec::to_value(&res) {
+                    Ok(value) => reply.success(&value),
+                    Err(e) => {
+                        reply.error("encode_error", Some(&e.to_string()), &EncodableValue::Null)
+                    }
+                },
+                Err(e) => reply.error(&e.code, e.message.as_deref(), &e.details),
+            }
+        };
+
+        self.methods.insert(method.to_owned(), Box::new(handler));
+        self
+    }
+}
+
+impl StandardMethodHandler for MethodChannelRouter {
+    fn handle(&self, method: &str, args: EncodableValue, reply: StandardMethodReply) {
+        match self.methods.get(method) {
+            Some(handler) => handler(args, reply),
+            None => {
+                tracing::warn!(method, "unimplemented");
+                reply.not_implemented();
+            }
+        }
+    }
+}
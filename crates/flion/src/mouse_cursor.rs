@@ -22,12 +22,13 @@ impl StandardMethodHandler for MouseCursorHandler {
     fn handle(&self, method: &str, args: EncodableValue, reply: StandardMethodReply) {
         match method {
             "activateSystemCursor" => {
-                let args = args.as_map().unwrap();
-                let kind = args
-                    .get(&EncodableValue::Str("kind"))
-                    .unwrap()
-                    .as_string()
-                    .unwrap();
+                let kind = match args.field::<&str>("kind") {
+                    Ok(kind) => kind,
+                    Err(e) => {
+                        reply.error("invalid_args", Some(&e.to_string()), &EncodableValue::Null);
+                        return;
+                    }
+                };
 
                 if let Some(window) = self.window.upgrade() {
                     window.set_cursor(get_cursor(kind));
@@ -0,0 +1,76 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, RwLock};
+
+/// Describes a panic that was caught at the boundary with the engine, before it could unwind into
+/// foreign code and abort the process.
+#[derive(Debug)]
+pub struct CallbackPanic<'a> {
+    /// The name of the engine callback that panicked.
+    pub callback: &'a str,
+    /// The channel whose handler panicked, if the panic was in a platform message handler.
+    pub channel: Option<&'a str>,
+    pub message: &'a str,
+}
+
+type PanicHook = Arc<dyn Fn(&CallbackPanic) + Send + Sync>;
+
+static PANIC_HOOK: RwLock<Option<PanicHook>> = RwLock::new(None);
+
+pub(crate) fn set_panic_hook(hook: Option<PanicHook>) {
+    *PANIC_HOOK.write().unwrap_or_else(|e| e.into_inner()) = hook;
+}
+
+/// Runs `f`, catching any panic so that it doesn't unwind across an `extern "C"` boundary. Panics
+/// are logged and passed to the app's panic hook, and `None` is returned.
+pub(crate) fn catch_panic<R>(callback: &str, f: impl FnOnce() -> R) -> Option<R> {
+    catch_panic_in_channel(callback, None, f).ok()
+}
+
+/// Like [catch_panic], but for platform message handlers. The panic message is returned so that
+/// it can be sent back to Dart.
+pub(crate) fn catch_panic_in_channel<R>(
+    callback: &str,
+    channel: Option<&str>,
+    f: impl FnOnce() -> R,
+) -> Result<R, String> {
+    // Handlers remain registered after panicking, so any state they share must tolerate being
+    // left midway through an update. This is the same as with parking_lot locks, which don't
+    // poison.
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(res) => Ok(res),
+        Err(payload) => {
+            let message = panic_message(&*payload);
+
+            tracing::error!(callback, channel, "panic in engine callback: {message}");
+
+            // The hook is cloned out so that it can't deadlock by setting a new hook.
+            let hook = PANIC_HOOK.read().unwrap_or_else(|e| e.into_inner()).clone();
+
+            if let Some(hook) = hook {
+                let info = CallbackPanic {
+                    callback,
+                    channel,
+                    message,
+                };
+
+                // A panicking hook must not escape either.
+                if panic::catch_unwind(AssertUnwindSafe(|| hook(&info))).is_err() {
+                    tracing::error!("panic hook panicked");
+                }
+            }
+
+            Err(message.to_owned())
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "Box<dyn Any>"
+    }
+}
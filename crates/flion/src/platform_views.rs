@@ -47,7 +47,7 @@ impl PlatformViewsGuard<'_> {
 pub trait PlatformView: Send + Sync {
     fn visual(&mut self) -> &IDCompositionVisual;
 
-    fn update(&mut self, args: &PlatformViewUpdateArgs) -> eyre::Result<()> {
+    fn update(&mut self, args: &PlatformViewUpdateArgs) -> crate::Result<()> {
         let _ = args;
         Ok(())
     }
@@ -76,19 +76,19 @@ pub trait PlatformViewFactory {
         context: CompositorContext,
         id: i32,
         args: EncodableValue,
-    ) -> eyre::Result<Box<dyn PlatformView>>;
+    ) -> crate::Result<Box<dyn PlatformView>>;
 }
 
 impl<F> PlatformViewFactory for F
 where
-    F: Fn(CompositorContext, i32, EncodableValue) -> eyre::Result<Box<dyn PlatformView>>,
+    F: Fn(CompositorContext, i32, EncodableValue) -> crate::Result<Box<dyn PlatformView>>,
 {
     fn create(
         &self,
         context: CompositorContext,
         id: i32,
         args: EncodableValue,
-    ) -> eyre::Result<Box<dyn PlatformView>> {
+    ) -> crate::Result<Box<dyn PlatformView>> {
         self(context, id, args)
     }
 }
@@ -116,6 +116,40 @@ impl PlatformViewsMessageHandler {
     }
 }
 
+impl PlatformViewsMessageHandler {
+    fn create(&self, args: &EncodableValue) -> eyre::Result<()> {
+        let id = args.field::<i32>("id")?;
+        let type_ = args.field::<&str>("type")?;
+        let create_args = args
+            .optional_field::<&EncodableValue>("args")?
+            .cloned()
+            .unwrap_or(EncodableValue::Null);
+
+        let Some(factory) = self.factories.get(type_) else {
+            eyre::bail!("no platform view factory registered for type `{type_}`");
+        };
+
+        let context = CompositorContext {
+            d3d11_device: &self.d3d11_device,
+            composition_device: &self.composition_device,
+        };
+
+        let platform_view = factory.create(context, id, create_args)?;
+
+        self.platform_views.add(id as u64, platform_view);
+
+        Ok(())
+    }
+
+    fn destroy(&self, args: &EncodableValue) -> eyre::Result<()> {
+        let id = args.field::<i32>("id")?;
+
+        self.platform_views.remove(id as u64);
+
+        Ok(())
+    }
+}
+
 impl StandardMethodHandler for PlatformViewsMessageHandler {
     fn handle(
         &self,
@@ -123,52 +157,21 @@ impl StandardMethodHandler for PlatformViewsMessageHandler {
         args: codec::EncodableValue,
         reply: standard_method_channel::StandardMethodReply,
     ) {
-        if method == "create" {
-            let mut args = args.into_map().unwrap();
-
-            let id = *args
-                .get(&EncodableValue::Str("id"))
-                .unwrap()
-                .as_i32()
-                .unwrap();
-
-            let type_ = args
-                .get(&EncodableValue::Str("type"))
-                .unwrap()
-                .as_string()
-                .unwrap();
-
-            let create_args = args
-                .remove(&EncodableValue::Str("args"))
-                .unwrap_or(EncodableValue::Null);
-
-            let context = CompositorContext {
-                d3d11_device: &self.d3d11_device,
-                composition_device: &self.composition_device,
-            };
-
-            // TODO: Return an error instead of unwrapping
-            let platform_view = self.factories[type_]
-                .create(context, id, create_args)
-                .unwrap();
-
-            self.platform_views.add(id as u64, platform_view);
-
-            reply.success(&EncodableValue::Null);
-        } else if method == "destroy" {
-            let args = args.into_map().unwrap();
-
-            let id = *args
-                .get(&EncodableValue::Str("id"))
-                .unwrap()
-                .as_i32()
-                .unwrap();
-
-            self.platform_views.remove(id as u64);
-
-            reply.success(&EncodableValue::Null);
-        } else {
-            reply.not_implemented();
+        let res = match method {
+            "create" => self.create(&args),
+            "destroy" => self.destroy(&args),
+            _ => {
+                reply.not_implemented();
+                return;
+            }
+        };
+
+        match res {
+            Ok(()) => reply.success(&EncodableValue::Null),
+            Err(e) => {
+                tracing::error!(method, "{e:?}");
+                reply.error("error", Some(&e.to_string()), &EncodableValue::Null);
+            }
         }
     }
 }
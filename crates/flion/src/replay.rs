@@ -0,0 +1,129 @@
+use std::cell::RefCell;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{BinaryMessageReply, PlatformMessageHandlers, TrafficDirection, TrafficRecord};
+use crate::error::Error;
+
+/// A platform message sent by Dart, and the reply that it received.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedMessage {
+    pub channel: String,
+    pub message: Vec<u8>,
+    /// The recorded reply, or `None` if the message hadn't been replied to when it was recorded.
+    /// Not implemented replies are recorded as empty.
+    pub reply: Option<Vec<u8>>,
+}
+
+/// The platform messages received from Dart during a session, which can be saved to a file and
+/// replayed against handlers in tests.
+///
+/// A recording is captured from the messages recorded by a
+/// [TrafficInspector](crate::TrafficInspector), so its capacity should be large enough to hold
+/// the whole session.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageRecording {
+    pub messages: Vec<RecordedMessage>,
+}
+
+impl MessageRecording {
+    /// Creates a recording from the inbound messages in `records`.
+    pub fn from_traffic(records: &[TrafficRecord]) -> MessageRecording {
+        let messages = records
+            .iter()
+            .filter(|record| record.direction == TrafficDirection::Inbound)
+            .map(|record| RecordedMessage {
+                channel: record.channel.clone(),
+                message: record.message.clone(),
+                reply: record.reply.as_ref().map(|reply| reply.message.clone()),
+            })
+            .collect();
+
+        MessageRecording { messages }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> crate::Result<MessageRecording> {
+        let bytes = fs::read(path).map_err(Error::Io)?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> crate::Result<()> {
+        let bytes = serde_json::to_vec(self)?;
+        fs::write(path, bytes).map_err(Error::Io)
+    }
+
+    /// Passes each recorded message to `handlers` in order, and checks that the replies match the
+    /// recorded ones. Messages on channels in `ignored_channels` are skipped, as are messages that
+    /// had no recorded reply.
+    ///
+    /// Handlers must reply before returning, since there is no task executor to run futures
+    /// during a replay.
+    pub fn replay(
+        &self,
+        handlers: &PlatformMessageHandlers,
+        ignored_channels: &[&str],
+    ) -> Result<(), ReplayMismatch> {
+        for (i, recorded) in self.messages.iter().enumerate() {
+            if ignored_channels.contains(&recorded.channel.as_str()) {
+                continue;
+            }
+
+            let Some(expected) = &recorded.reply else {
+                continue;
+            };
+
+            let actual = Rc::new(RefCell::new(None));
+            let reply = BinaryMessageReply::from_fn({
+                let actual = actual.clone();
+                move |reply| *actual.borrow_mut() = Some(reply.to_vec())
+            });
+
+            handlers.dispatch(&recorded.channel, &recorded.message, reply);
+
+            let actual = actual.take();
+            if actual.as_ref() != Some(expected) {
+                return Err(ReplayMismatch {
+                    index: i,
+                    channel: recorded.channel.clone(),
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A reply during a replay that didn't match the recorded one.
+#[derive(Debug)]
+pub struct ReplayMismatch {
+    /// The index of the message in the recording.
+    pub index: usize,
+    pub channel: String,
+    pub expected: Vec<u8>,
+    /// The actual reply, or `None` if the handler didn't reply.
+    pub actual: Option<Vec<u8>>,
+}
+
+impl Display for ReplayMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ReplayMismatch { index, channel, .. } = self;
+
+        match &self.actual {
+            None => write!(f, "message {index} on {channel} was not replied to"),
+            Some(actual) => write!(
+                f,
+                "reply to message {index} on {channel} does not match\n  \
+                 expected: {:02x?}\n    actual: {actual:02x?}",
+                self.expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayMismatch {}
@@ -0,0 +1,385 @@
+#[cfg(feature = "accesskit")]
+pub mod accesskit;
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::{c_char, CStr};
+use std::slice;
+
+use bitflags::bitflags;
+use flutter_embedder::{
+    FlutterSemanticsAction, FlutterSemanticsAction_kFlutterSemanticsActionCopy,
+    FlutterSemanticsAction_kFlutterSemanticsActionCustomAction,
+    FlutterSemanticsAction_kFlutterSemanticsActionCut,
+    FlutterSemanticsAction_kFlutterSemanticsActionDecrease,
+    FlutterSemanticsAction_kFlutterSemanticsActionDidGainAccessibilityFocus,
+    FlutterSemanticsAction_kFlutterSemanticsActionDidLoseAccessibilityFocus,
+    FlutterSemanticsAction_kFlutterSemanticsActionDismiss,
+    FlutterSemanticsAction_kFlutterSemanticsActionFocus,
+    FlutterSemanticsAction_kFlutterSemanticsActionIncrease,
+    FlutterSemanticsAction_kFlutterSemanticsActionLongPress,
+    FlutterSemanticsAction_kFlutterSemanticsActionMoveCursorBackwardByCharacter,
+    FlutterSemanticsAction_kFlutterSemanticsActionMoveCursorBackwardByWord,
+    FlutterSemanticsAction_kFlutterSemanticsActionMoveCursorForwardByCharacter,
+    FlutterSemanticsAction_kFlutterSemanticsActionMoveCursorForwardByWord,
+    FlutterSemanticsAction_kFlutterSemanticsActionPaste,
+    FlutterSemanticsAction_kFlutterSemanticsActionScrollDown,
+    FlutterSemanticsAction_kFlutterSemanticsActionScrollLeft,
+    FlutterSemanticsAction_kFlutterSemanticsActionScrollRight,
+    FlutterSemanticsAction_kFlutterSemanticsActionScrollToOffset,
+    FlutterSemanticsAction_kFlutterSemanticsActionScrollUp,
+    FlutterSemanticsAction_kFlutterSemanticsActionSetSelection,
+    FlutterSemanticsAction_kFlutterSemanticsActionSetText,
+    FlutterSemanticsAction_kFlutterSemanticsActionShowOnScreen,
+    FlutterSemanticsAction_kFlutterSemanticsActionTap, FlutterSemanticsCustomAction2,
+    FlutterSemanticsFlag, FlutterSemanticsFlag_kFlutterSemanticsFlagHasCheckedState,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagHasEnabledState,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagHasExpandedState,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagHasImplicitScrolling,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagHasSelectedState,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagHasToggledState,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsButton,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsCheckStateMixed,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsChecked,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsEnabled,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsExpanded,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsFocusable,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsFocused,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsHeader,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsHidden,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsImage,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsInMutuallyExclusiveGroup,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsKeyboardKey,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsLink,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsLiveRegion,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsMultiline,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsObscured,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsReadOnly,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsSelected,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsSlider,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsTextField,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagIsToggled,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagNamesRoute,
+    FlutterSemanticsFlag_kFlutterSemanticsFlagScopesRoute, FlutterSemanticsNode2,
+    FlutterSemanticsUpdate2, FlutterTextDirection_kFlutterTextDirectionLTR,
+    FlutterTextDirection_kFlutterTextDirectionRTL,
+};
+
+bitflags! {
+    /// Properties of a semantics node, like `SemanticsFlag` in Dart.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct SemanticsFlags: FlutterSemanticsFlag {
+        const HAS_CHECKED_STATE = FlutterSemanticsFlag_kFlutterSemanticsFlagHasCheckedState;
+        const IS_CHECKED = FlutterSemanticsFlag_kFlutterSemanticsFlagIsChecked;
+        const IS_SELECTED = FlutterSemanticsFlag_kFlutterSemanticsFlagIsSelected;
+        const IS_BUTTON = FlutterSemanticsFlag_kFlutterSemanticsFlagIsButton;
+        const IS_TEXT_FIELD = FlutterSemanticsFlag_kFlutterSemanticsFlagIsTextField;
+        const IS_FOCUSED = FlutterSemanticsFlag_kFlutterSemanticsFlagIsFocused;
+        const HAS_ENABLED_STATE = FlutterSemanticsFlag_kFlutterSemanticsFlagHasEnabledState;
+        const IS_ENABLED = FlutterSemanticsFlag_kFlutterSemanticsFlagIsEnabled;
+        const IS_IN_MUTUALLY_EXCLUSIVE_GROUP =
+            FlutterSemanticsFlag_kFlutterSemanticsFlagIsInMutuallyExclusiveGroup;
+        const IS_HEADER = FlutterSemanticsFlag_kFlutterSemanticsFlagIsHeader;
+        const IS_OBSCURED = FlutterSemanticsFlag_kFlutterSemanticsFlagIsObscured;
+        const SCOPES_ROUTE = FlutterSemanticsFlag_kFlutterSemanticsFlagScopesRoute;
+        const NAMES_ROUTE = FlutterSemanticsFlag_kFlutterSemanticsFlagNamesRoute;
+        const IS_HIDDEN = FlutterSemanticsFlag_kFlutterSemanticsFlagIsHidden;
+        const IS_IMAGE = FlutterSemanticsFlag_kFlutterSemanticsFlagIsImage;
+        const IS_LIVE_REGION = FlutterSemanticsFlag_kFlutterSemanticsFlagIsLiveRegion;
+        const HAS_TOGGLED_STATE = FlutterSemanticsFlag_kFlutterSemanticsFlagHasToggledState;
+        const IS_TOGGLED = FlutterSemanticsFlag_kFlutterSemanticsFlagIsToggled;
+        const HAS_IMPLICIT_SCROLLING =
+            FlutterSemanticsFlag_kFlutterSemanticsFlagHasImplicitScrolling;
+        const IS_MULTILINE = FlutterSemanticsFlag_kFlutterSemanticsFlagIsMultiline;
+        const IS_READ_ONLY = FlutterSemanticsFlag_kFlutterSemanticsFlagIsReadOnly;
+        const IS_FOCUSABLE = FlutterSemanticsFlag_kFlutterSemanticsFlagIsFocusable;
+        const IS_LINK = FlutterSemanticsFlag_kFlutterSemanticsFlagIsLink;
+        const IS_SLIDER = FlutterSemanticsFlag_kFlutterSemanticsFlagIsSlider;
+        const IS_KEYBOARD_KEY = FlutterSemanticsFlag_kFlutterSemanticsFlagIsKeyboardKey;
+        const IS_CHECK_STATE_MIXED = FlutterSemanticsFlag_kFlutterSemanticsFlagIsCheckStateMixed;
+        const HAS_EXPANDED_STATE = FlutterSemanticsFlag_kFlutterSemanticsFlagHasExpandedState;
+        const IS_EXPANDED = FlutterSemanticsFlag_kFlutterSemanticsFlagIsExpanded;
+        const HAS_SELECTED_STATE = FlutterSemanticsFlag_kFlutterSemanticsFlagHasSelectedState;
+    }
+}
+
+bitflags! {
+    /// Actions that can be performed on a semantics node, like `SemanticsAction` in Dart.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct SemanticsActions: FlutterSemanticsAction {
+        const TAP = FlutterSemanticsAction_kFlutterSemanticsActionTap;
+        const LONG_PRESS = FlutterSemanticsAction_kFlutterSemanticsActionLongPress;
+        const SCROLL_LEFT = FlutterSemanticsAction_kFlutterSemanticsActionScrollLeft;
+        const SCROLL_RIGHT = FlutterSemanticsAction_kFlutterSemanticsActionScrollRight;
+        const SCROLL_UP = FlutterSemanticsAction_kFlutterSemanticsActionScrollUp;
+        const SCROLL_DOWN = FlutterSemanticsAction_kFlutterSemanticsActionScrollDown;
+        const INCREASE = FlutterSemanticsAction_kFlutterSemanticsActionIncrease;
+        const DECREASE = FlutterSemanticsAction_kFlutterSemanticsActionDecrease;
+        const SHOW_ON_SCREEN = FlutterSemanticsAction_kFlutterSemanticsActionShowOnScreen;
+        const MOVE_CURSOR_FORWARD_BY_CHARACTER =
+            FlutterSemanticsAction_kFlutterSemanticsActionMoveCursorForwardByCharacter;
+        const MOVE_CURSOR_BACKWARD_BY_CHARACTER =
+            FlutterSemanticsAction_kFlutterSemanticsActionMoveCursorBackwardByCharacter;
+        const SET_SELECTION = FlutterSemanticsAction_kFlutterSemanticsActionSetSelection;
+        const COPY = FlutterSemanticsAction_kFlutterSemanticsActionCopy;
+        const CUT = FlutterSemanticsAction_kFlutterSemanticsActionCut;
+        const PASTE = FlutterSemanticsAction_kFlutterSemanticsActionPaste;
+        const DID_GAIN_ACCESSIBILITY_FOCUS =
+            FlutterSemanticsAction_kFlutterSemanticsActionDidGainAccessibilityFocus;
+        const DID_LOSE_ACCESSIBILITY_FOCUS =
+            FlutterSemanticsAction_kFlutterSemanticsActionDidLoseAccessibilityFocus;
+        const CUSTOM_ACTION = FlutterSemanticsAction_kFlutterSemanticsActionCustomAction;
+        const DISMISS = FlutterSemanticsAction_kFlutterSemanticsActionDismiss;
+        const MOVE_CURSOR_FORWARD_BY_WORD =
+            FlutterSemanticsAction_kFlutterSemanticsActionMoveCursorForwardByWord;
+        const MOVE_CURSOR_BACKWARD_BY_WORD =
+            FlutterSemanticsAction_kFlutterSemanticsActionMoveCursorBackwardByWord;
+        const SET_TEXT = FlutterSemanticsAction_kFlutterSemanticsActionSetText;
+        const FOCUS = FlutterSemanticsAction_kFlutterSemanticsActionFocus;
+        const SCROLL_TO_OFFSET = FlutterSemanticsAction_kFlutterSemanticsActionScrollToOffset;
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextDirection {
+    #[default]
+    Unknown,
+    Rtl,
+    Ltr,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub left: f64,
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+}
+
+/// A node in the semantics tree, like `SemanticsNode` in Dart.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SemanticsNode {
+    pub id: i32,
+    pub flags: SemanticsFlags,
+    pub actions: SemanticsActions,
+    pub label: String,
+    pub hint: String,
+    pub value: String,
+    pub increased_value: String,
+    pub decreased_value: String,
+    pub tooltip: String,
+    pub text_direction: TextDirection,
+    /// The base and extent of the text selection, if the node has one.
+    pub text_selection: Option<(i32, i32)>,
+    pub scroll_child_count: i32,
+    pub scroll_index: i32,
+    pub scroll_position: f64,
+    pub scroll_extent_max: f64,
+    pub scroll_extent_min: f64,
+    /// The bounds of the node in its own coordinate system.
+    pub rect: Rect,
+    /// The row-major 3x3 transform from the node's coordinate system to its parent's.
+    pub transform: [f64; 9],
+    /// The ids of the node's children in traversal order.
+    pub children: Vec<i32>,
+    /// The ids of the custom actions that can be performed on the node.
+    pub custom_actions: Vec<i32>,
+    pub platform_view_id: Option<i64>,
+}
+
+impl SemanticsNode {
+    unsafe fn from_raw(node: &FlutterSemanticsNode2) -> SemanticsNode {
+        let t = &node.transform;
+
+        SemanticsNode {
+            id: node.id,
+            flags: SemanticsFlags::from_bits_retain(node.flags),
+            actions: SemanticsActions::from_bits_retain(node.actions),
+            label: string_from_raw(node.label),
+            hint: string_from_raw(node.hint),
+            value: string_from_raw(node.value),
+            increased_value: string_from_raw(node.increased_value),
+            decreased_value: string_from_raw(node.decreased_value),
+            tooltip: string_from_raw(node.tooltip),
+            #[expect(non_upper_case_globals)]
+            text_direction: match node.text_direction {
+                FlutterTextDirection_kFlutterTextDirectionRTL => TextDirection::Rtl,
+                FlutterTextDirection_kFlutterTextDirectionLTR => TextDirection::Ltr,
+                _ => TextDirection::Unknown,
+            },
+            text_selection: (node.text_selection_base >= 0 && node.text_selection_extent >= 0)
+                .then_some((node.text_selection_base, node.text_selection_extent)),
+            scroll_child_count: node.scroll_child_count,
+            scroll_index: node.scroll_index,
+            scroll_position: node.scroll_position,
+            scroll_extent_max: node.scroll_extent_max,
+            scroll_extent_min: node.scroll_extent_min,
+            rect: Rect {
+                left: node.rect.left,
+                top: node.rect.top,
+                right: node.rect.right,
+                bottom: node.rect.bottom,
+            },
+            transform: [
+                t.scaleX, t.skewX, t.transX, t.skewY, t.scaleY, t.transY, t.pers0, t.pers1, t.pers2,
+            ],
+            children: slice_from_raw(node.children_in_traversal_order, node.child_count).to_vec(),
+            custom_actions: slice_from_raw(
+                node.custom_accessibility_actions,
+                node.custom_accessibility_actions_count,
+            )
+            .to_vec(),
+            platform_view_id: (node.platform_view_id >= 0).then_some(node.platform_view_id),
+        }
+    }
+}
+
+/// An action defined by the app with `CustomSemanticsAction` in Dart, or an override of the label
+/// of a standard action.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CustomSemanticsAction {
+    pub id: i32,
+    /// The standard action that this overrides, if any.
+    pub override_action: Option<SemanticsActions>,
+    pub label: String,
+    pub hint: String,
+}
+
+impl CustomSemanticsAction {
+    unsafe fn from_raw(action: &FlutterSemanticsCustomAction2) -> CustomSemanticsAction {
+        CustomSemanticsAction {
+            id: action.id,
+            override_action: (action.override_action != 0)
+                .then(|| SemanticsActions::from_bits_retain(action.override_action)),
+            label: string_from_raw(action.label),
+            hint: string_from_raw(action.hint),
+        }
+    }
+}
+
+/// The nodes and custom actions that were added or changed in a frame.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SemanticsUpdate {
+    pub nodes: Vec<SemanticsNode>,
+    pub custom_actions: Vec<CustomSemanticsAction>,
+}
+
+impl SemanticsUpdate {
+    pub(crate) unsafe fn from_raw(update: &FlutterSemanticsUpdate2) -> SemanticsUpdate {
+        SemanticsUpdate {
+            nodes: slice_from_raw(update.nodes, update.node_count)
+                .iter()
+                .map(|&node| SemanticsNode::from_raw(&*node))
+                .collect(),
+            custom_actions: slice_from_raw(update.custom_actions, update.custom_action_count)
+                .iter()
+                .map(|&action| CustomSemanticsAction::from_raw(&*action))
+                .collect(),
+        }
+    }
+}
+
+/// The semantics tree of the app, which is kept up to date while semantics are enabled.
+#[derive(Clone, Debug, Default)]
+pub struct SemanticsTree {
+    nodes: HashMap<i32, SemanticsNode>,
+    custom_actions: HashMap<i32, CustomSemanticsAction>,
+}
+
+impl SemanticsTree {
+    pub const ROOT_ID: i32 = 0;
+
+    pub fn root(&self) -> Option<&SemanticsNode> {
+        self.node(Self::ROOT_ID)
+    }
+
+    pub fn node(&self, id: i32) -> Option<&SemanticsNode> {
+        self.nodes.get(&id)
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &SemanticsNode> {
+        self.nodes.values()
+    }
+
+    pub fn custom_action(&self, id: i32) -> Option<&CustomSemanticsAction> {
+        self.custom_actions.get(&id)
+    }
+
+    /// Returns the node with input focus, if any.
+    pub fn focused(&self) -> Option<&SemanticsNode> {
+        self.nodes
+            .values()
+            .find(|node| node.flags.contains(SemanticsFlags::IS_FOCUSED))
+    }
+
+    /// Applies an update to the tree, and returns the ids of the nodes that were removed because
+    /// they are no longer reachable from the root.
+    pub fn apply(&mut self, update: SemanticsUpdate) -> Vec<i32> {
+        for node in update.nodes {
+            self.nodes.insert(node.id, node);
+        }
+
+        for action in update.custom_actions {
+            self.custom_actions.insert(action.id, action);
+        }
+
+        let mut reachable = HashSet::new();
+        let mut stack = vec![Self::ROOT_ID];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = self.nodes.get(&id)
+                && reachable.insert(id)
+            {
+                stack.extend(&node.children);
+            }
+        }
+
+        let removed = self
+            .nodes
+            .keys()
+            .copied()
+            .filter(|id| !reachable.contains(id))
+            .collect::<Vec<_>>();
+
+        for id in &removed {
+            self.nodes.remove(id);
+        }
+
+        removed
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.custom_actions.clear();
+    }
+}
+
+/// Receives changes to the semantics tree, for example to expose it to assistive technology.
+pub trait SemanticsHandler {
+    /// Called after `update` has been applied to `tree`. `removed` contains the ids of the nodes
+    /// that were removed from the tree.
+    fn on_update(&self, tree: &SemanticsTree, update: &SemanticsUpdate, removed: &[i32]);
+}
+
+impl<F> SemanticsHandler for F
+where
+    F: Fn(&SemanticsTree, &SemanticsUpdate, &[i32]),
+{
+    fn on_update(&self, tree: &SemanticsTree, update: &SemanticsUpdate, removed: &[i32]) {
+        self(tree, update, removed)
+    }
+}
+
+unsafe fn string_from_raw(s: *const c_char) -> String {
+    if s.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(s).to_string_lossy().into_owned()
+    }
+}
+
+unsafe fn slice_from_raw<'a, T>(ptr: *const T, len: usize) -> &'a [T] {
+    if ptr.is_null() || len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(ptr, len)
+    }
+}
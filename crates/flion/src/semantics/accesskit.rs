@@ -0,0 +1,280 @@
+use ::accesskit::{
+    Action, ActionData, ActionRequest, Affine, CustomAction, Live, Node, NodeId, Rect, Role,
+    Toggled, Tree, TreeUpdate,
+};
+
+use super::{
+    SemanticsActions, SemanticsFlags, SemanticsHandler, SemanticsNode, SemanticsTree,
+    SemanticsUpdate,
+};
+use crate::basic_message_channel::{MessageCodec, StandardMessageCodec};
+use crate::codec::EncodableValue;
+
+/// A [SemanticsHandler] that converts semantics updates to [AccessKit](https://accesskit.dev) tree
+/// updates, and passes them to a platform adapter such as
+/// `accesskit_windows::Adapter::update_if_active` so that they are exposed to assistive technology.
+pub struct AccessKitAdapter<F> {
+    update: F,
+}
+
+impl<F: Fn(TreeUpdate)> AccessKitAdapter<F> {
+    pub fn new(update: F) -> AccessKitAdapter<F> {
+        AccessKitAdapter { update }
+    }
+}
+
+impl<F: Fn(TreeUpdate)> SemanticsHandler for AccessKitAdapter<F> {
+    fn on_update(&self, tree: &SemanticsTree, update: &SemanticsUpdate, _removed: &[i32]) {
+        // Removed nodes don't need to be sent, since AccessKit drops nodes that are no longer
+        // children of another node.
+        let nodes = update
+            .nodes
+            .iter()
+            .filter_map(|node| Some((node_id(node.id)?, convert_node(tree, node))))
+            .collect();
+
+        (self.update)(TreeUpdate {
+            nodes,
+            tree: Some(Tree::new(ROOT_ID)),
+            focus: focus(tree),
+        });
+    }
+}
+
+/// Returns an update containing the whole tree, which is needed when an AccessKit adapter is
+/// first activated.
+pub fn tree_update(tree: &SemanticsTree) -> TreeUpdate {
+    let mut nodes = tree
+        .nodes()
+        .filter_map(|node| Some((node_id(node.id)?, convert_node(tree, node))))
+        .collect::<Vec<_>>();
+
+    // The tree must always contain the root, even before Dart has sent any semantics.
+    if tree.root().is_none() {
+        nodes.push((ROOT_ID, Node::new(Role::Window)));
+    }
+
+    TreeUpdate {
+        nodes,
+        tree: Some(Tree::new(ROOT_ID)),
+        focus: focus(tree),
+    }
+}
+
+/// A semantics action to dispatch to the engine, which can be passed to
+/// [FlionApp::dispatch_semantics_action](crate::FlionApp::dispatch_semantics_action).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SemanticsActionRequest {
+    pub node_id: i32,
+    pub action: SemanticsActions,
+    /// The action's arguments, encoded with the standard message codec.
+    pub args: Vec<u8>,
+}
+
+/// Converts an action requested by assistive technology to a semantics action, or returns `None`
+/// if it isn't supported.
+pub fn semantics_action(request: &ActionRequest) -> Option<SemanticsActionRequest> {
+    let encode = |value: EncodableValue| StandardMessageCodec::default().encode_message(&value);
+
+    // Flutter's scroll actions are named after the direction that content moves, which is the
+    // opposite of AccessKit's.
+    let (action, args) = match (request.action, &request.data) {
+        (Action::Click, _) => (SemanticsActions::TAP, vec![]),
+        (Action::Focus, _) => (SemanticsActions::FOCUS, vec![]),
+        (Action::Increment, _) => (SemanticsActions::INCREASE, vec![]),
+        (Action::Decrement, _) => (SemanticsActions::DECREASE, vec![]),
+        (Action::ShowContextMenu, _) => (SemanticsActions::LONG_PRESS, vec![]),
+        (Action::ScrollIntoView, _) => (SemanticsActions::SHOW_ON_SCREEN, vec![]),
+        (Action::ScrollUp, _) => (SemanticsActions::SCROLL_DOWN, vec![]),
+        (Action::ScrollDown, _) => (SemanticsActions::SCROLL_UP, vec![]),
+        (Action::ScrollLeft, _) => (SemanticsActions::SCROLL_RIGHT, vec![]),
+        (Action::ScrollRight, _) => (SemanticsActions::SCROLL_LEFT, vec![]),
+        (Action::SetValue, Some(ActionData::Value(value))) => (
+            SemanticsActions::SET_TEXT,
+            encode(EncodableValue::Str(value.as_ref().into())).ok()?,
+        ),
+        (Action::CustomAction, Some(ActionData::CustomAction(id))) => (
+            SemanticsActions::CUSTOM_ACTION,
+            encode(EncodableValue::I32(*id)).ok()?,
+        ),
+        _ => return None,
+    };
+
+    Some(SemanticsActionRequest {
+        node_id: i32::try_from(request.target.0).ok()?,
+        action,
+        args,
+    })
+}
+
+const ROOT_ID: NodeId = NodeId(SemanticsTree::ROOT_ID as u64);
+
+/// Converts a semantics node id to an AccessKit node id. Flutter never assigns negative ids to
+/// nodes, so these are rejected rather than wrapped around.
+fn node_id(id: i32) -> Option<NodeId> {
+    u64::try_from(id).ok().map(NodeId)
+}
+
+fn focus(tree: &SemanticsTree) -> NodeId {
+    tree.focused()
+        .and_then(|node| node_id(node.id))
+        .unwrap_or(ROOT_ID)
+}
+
+fn convert_node(tree: &SemanticsTree, node: &SemanticsNode) -> Node {
+    let flags = node.flags;
+
+    let mut result = Node::new(role(node));
+
+    if !node.label.is_empty() {
+        result.set_label(node.label.as_str());
+    }
+
+    if !node.value.is_empty() {
+        result.set_value(node.value.as_str());
+    }
+
+    if !node.hint.is_empty() {
+        result.set_description(node.hint.as_str());
+    } else if !node.tooltip.is_empty() {
+        result.set_description(node.tooltip.as_str());
+    }
+
+    let rect = &node.rect;
+    result.set_bounds(Rect::new(rect.left, rect.top, rect.right, rect.bottom));
+
+    // AccessKit transforms are affine, so the perspective row is dropped.
+    let [scale_x, skew_x, trans_x, skew_y, scale_y, trans_y, ..] = node.transform;
+    let transform = Affine::new([scale_x, skew_y, skew_x, scale_y, trans_x, trans_y]);
+    if transform != Affine::IDENTITY {
+        result.set_transform(transform);
+    }
+
+    result.set_children(
+        node.children
+            .iter()
+            .copied()
+            .filter_map(node_id)
+            .collect::<Vec<_>>(),
+    );
+
+    if flags.contains(SemanticsFlags::HAS_ENABLED_STATE)
+        && !flags.contains(SemanticsFlags::IS_ENABLED)
+    {
+        result.set_disabled();
+    }
+
+    if flags.contains(SemanticsFlags::HAS_CHECKED_STATE) {
+        result.set_toggled(if flags.contains(SemanticsFlags::IS_CHECK_STATE_MIXED) {
+            Toggled::Mixed
+        } else {
+            toggled(flags.contains(SemanticsFlags::IS_CHECKED))
+        });
+    } else if flags.contains(SemanticsFlags::HAS_TOGGLED_STATE) {
+        result.set_toggled(toggled(flags.contains(SemanticsFlags::IS_TOGGLED)));
+    }
+
+    if flags.contains(SemanticsFlags::HAS_SELECTED_STATE) {
+        result.set_selected(flags.contains(SemanticsFlags::IS_SELECTED));
+    }
+
+    if flags.contains(SemanticsFlags::HAS_EXPANDED_STATE) {
+        result.set_expanded(flags.contains(SemanticsFlags::IS_EXPANDED));
+    }
+
+    if flags.contains(SemanticsFlags::IS_READ_ONLY) {
+        result.set_read_only();
+    }
+
+    if flags.contains(SemanticsFlags::IS_HIDDEN) {
+        result.set_hidden();
+    }
+
+    if flags.contains(SemanticsFlags::IS_LIVE_REGION) {
+        result.set_live(Live::Polite);
+    }
+
+    for (flutter_action, action) in [
+        (SemanticsActions::TAP, Action::Click),
+        (SemanticsActions::FOCUS, Action::Focus),
+        (SemanticsActions::INCREASE, Action::Increment),
+        (SemanticsActions::DECREASE, Action::Decrement),
+        (SemanticsActions::LONG_PRESS, Action::ShowContextMenu),
+        (SemanticsActions::SHOW_ON_SCREEN, Action::ScrollIntoView),
+        (SemanticsActions::SCROLL_DOWN, Action::ScrollUp),
+        (SemanticsActions::SCROLL_UP, Action::ScrollDown),
+        (SemanticsActions::SCROLL_RIGHT, Action::ScrollLeft),
+        (SemanticsActions::SCROLL_LEFT, Action::ScrollRight),
+        (SemanticsActions::SET_TEXT, Action::SetValue),
+    ] {
+        if node.actions.contains(flutter_action) {
+            result.add_action(action);
+        }
+    }
+
+    // Overrides of standard actions only change how they are announced, so only actions defined
+    // by the app are exposed.
+    let custom_actions = node
+        .custom_actions
+        .iter()
+        .filter_map(|&id| tree.custom_action(id))
+        .filter(|action| action.override_action.is_none())
+        .map(|action| CustomAction {
+            id: action.id,
+            description: action.label.as_str().into(),
+        })
+        .collect::<Vec<_>>();
+
+    if !custom_actions.is_empty() {
+        result.add_action(Action::CustomAction);
+        result.set_custom_actions(custom_actions);
+    }
+
+    result
+}
+
+fn role(node: &SemanticsNode) -> Role {
+    let flags = node.flags;
+
+    if node.id == SemanticsTree::ROOT_ID {
+        Role::Window
+    } else if flags.contains(SemanticsFlags::IS_TEXT_FIELD) {
+        if flags.contains(SemanticsFlags::IS_OBSCURED) {
+            Role::PasswordInput
+        } else if flags.contains(SemanticsFlags::IS_MULTILINE) {
+            Role::MultilineTextInput
+        } else {
+            Role::TextInput
+        }
+    } else if flags.contains(SemanticsFlags::HAS_CHECKED_STATE) {
+        if flags.contains(SemanticsFlags::IS_IN_MUTUALLY_EXCLUSIVE_GROUP) {
+            Role::RadioButton
+        } else {
+            Role::CheckBox
+        }
+    } else if flags.contains(SemanticsFlags::HAS_TOGGLED_STATE) {
+        Role::Switch
+    } else if flags.contains(SemanticsFlags::IS_BUTTON) {
+        Role::Button
+    } else if flags.contains(SemanticsFlags::IS_SLIDER) {
+        Role::Slider
+    } else if flags.contains(SemanticsFlags::IS_LINK) {
+        Role::Link
+    } else if flags.contains(SemanticsFlags::IS_HEADER) {
+        Role::Heading
+    } else if flags.contains(SemanticsFlags::IS_IMAGE) {
+        Role::Image
+    } else if !node.label.is_empty() && node.children.is_empty() {
+        Role::Label
+    } else {
+        Role::GenericContainer
+    }
+}
+
+fn toggled(value: bool) -> Toggled {
+    if value {
+        Toggled::True
+    } else {
+        Toggled::False
+    }
+}
@@ -6,7 +6,7 @@ use windows::Win32::System::Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_R
 
 use crate::engine::FlutterEngine;
 
-pub fn send_to_engine(engine: &FlutterEngine) -> eyre::Result<()> {
+pub fn send_to_engine(engine: &FlutterEngine) -> crate::Result<()> {
     let mut use_light_theme = 0u32;
     let mut use_light_theme_size = mem::size_of_val(&use_light_theme) as u32;
     unsafe {
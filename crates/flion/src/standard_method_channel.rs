@@ -1,43 +1,516 @@
-use std::io::{Cursor, Write};
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::fmt::{self, Display};
+use std::future::Future;
+use std::io::{self, Cursor, Write};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::task::{Context, Poll, Waker};
 
-use crate::codec::{self, EncodableValue};
-use crate::engine::{BinaryMessageHandler, BinaryMessageReply};
+use crate::codec::{self, CodecExtension, DecodeLimits, Decoder, EncodableValue, NoExtension};
+use crate::engine::{BinaryMessageHandler, BinaryMessageReply, BinaryMessenger};
+use crate::error::Error;
+use crate::task_runner;
+
+/// Encoding for method calls and replies on channels that use `StandardMethodCodec` on the Dart
+/// side.
+#[derive(Clone, Copy)]
+pub struct StandardMethodCodec {
+    extension: &'static dyn CodecExtension,
+    limits: DecodeLimits,
+}
+
+pub struct StandardMethodCall<'a> {
+    pub method: String,
+    pub args: EncodableValue<'a>,
+}
+
+/// An error returned by a method call, which surfaces as a `PlatformException` in Dart.
+#[derive(Clone, Debug)]
+pub struct StandardMethodError {
+    pub code: String,
+    pub message: Option<String>,
+    pub details: EncodableValue<'static>,
+}
+
+impl Display for StandardMethodError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "{}: {message}", self.code),
+            None => write!(f, "{}", self.code),
+        }
+    }
+}
+
+impl StandardMethodError {
+    pub fn new(code: impl Into<String>, message: impl Into<String>) -> StandardMethodError {
+        StandardMethodError {
+            code: code.into(),
+            message: Some(message.into()),
+            details: EncodableValue::Null,
+        }
+    }
+}
+
+impl std::error::Error for StandardMethodError {}
+
+impl From<eyre::Report> for StandardMethodError {
+    fn from(e: eyre::Report) -> Self {
+        StandardMethodError::new("error", format!("{e:#}"))
+    }
+}
+
+impl From<Error> for StandardMethodError {
+    fn from(e: Error) -> Self {
+        StandardMethodError::new("error", e.to_string())
+    }
+}
+
+impl StandardMethodCodec {
+    pub fn with_extension(extension: &'static dyn CodecExtension) -> StandardMethodCodec {
+        StandardMethodCodec {
+            extension,
+            limits: DecodeLimits::default(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: DecodeLimits) -> StandardMethodCodec {
+        self.limits = limits;
+        self
+    }
+
+    pub fn extension(&self) -> &'static dyn CodecExtension {
+        self.extension
+    }
+
+    pub fn limits(&self) -> DecodeLimits {
+        self.limits
+    }
+
+    fn decoder(&self) -> Decoder<'static> {
+        Decoder::new(self.extension).with_limits(self.limits)
+    }
+
+    pub fn encode_method_call(&self, method: &str, args: &EncodableValue) -> io::Result<Vec<u8>> {
+        let mut bytes = vec![];
+        let mut cursor = Cursor::new(&mut bytes);
+        codec::write_value_with(&mut cursor, &EncodableValue::from(method), self.extension)?;
+        codec::write_value_with(&mut cursor, args, self.extension)?;
+        Ok(bytes)
+    }
+
+    pub fn decode_method_call<'a>(&self, message: &'a [u8]) -> io::Result<StandardMethodCall<'a>> {
+        let mut cursor = Cursor::new(message);
+        let mut decoder = self.decoder();
+
+        let method = decoder.read_value(&mut cursor)?;
+        let EncodableValue::Str(method) = method else {
+            return Err(invalid_data(format!("invalid method name: {method:?}")));
+        };
+
+        let args = decoder.read_value(&mut cursor)?;
+
+        Ok(StandardMethodCall {
+            method: method.into_owned(),
+            args,
+        })
+    }
+
+    pub fn encode_success_envelope(&self, result: &EncodableValue) -> io::Result<Vec<u8>> {
+        let mut bytes = vec![];
+        let mut cursor = Cursor::new(&mut bytes);
+        cursor.write_all(&[0])?;
+        codec::write_value_with(&mut cursor, result, self.extension)?;
+        Ok(bytes)
+    }
+
+    pub fn encode_error_envelope(
+        &self,
+        code: &str,
+        message: Option<&str>,
+        details: &EncodableValue,
+    ) -> io::Result<Vec<u8>> {
+        let mut bytes = vec![];
+        let mut cursor = Cursor::new(&mut bytes);
+        cursor.write_all(&[1])?;
+        codec::write_value_with(&mut cursor, &EncodableValue::from(code), self.extension)?;
+        codec::write_value_with(
+            &mut cursor,
+            &message.map_or(EncodableValue::Null, EncodableValue::from),
+            self.extension,
+        )?;
+        codec::write_value_with(&mut cursor, details, self.extension)?;
+        Ok(bytes)
+    }
+
+    /// Decodes a success or error envelope. An empty reply, which means that the method is not
+    /// implemented, must be handled by the caller.
+    pub fn decode_envelope<'a>(
+        &self,
+        envelope: &'a [u8],
+    ) -> io::Result<Result<EncodableValue<'a>, StandardMethodError>> {
+        let Some(&tag) = envelope.first() else {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        };
+
+        // Alignment of typed lists and doubles is relative to the start of the envelope, so the
+        // cursor must be over the whole buffer rather than the bytes after the tag.
+        let mut cursor = Cursor::new(envelope);
+        cursor.set_position(1);
+        let mut decoder = self.decoder();
+
+        match tag {
+            0 => Ok(Ok(decoder.read_value(&mut cursor)?)),
+            1 => {
+                let code = match decoder.read_value(&mut cursor)? {
+                    EncodableValue::Str(code) => code.into_owned(),
+                    code => return Err(invalid_data(format!("invalid error code: {code:?}"))),
+                };
+
+                let message = match decoder.read_value(&mut cursor)? {
+                    EncodableValue::Null => None,
+                    EncodableValue::Str(message) => Some(message.into_owned()),
+                    message => {
+                        return Err(invalid_data(format!("invalid error message: {message:?}")))
+                    }
+                };
+
+                // Android sends an additional stacktrace after the details, which is ignored.
+                let details = decoder.read_value(&mut cursor)?.into_owned();
+
+                Ok(Err(StandardMethodError {
+                    code,
+                    message,
+                    details,
+                }))
+            }
+            tag => Err(invalid_data(format!("invalid envelope type: {tag}"))),
+        }
+    }
+
+    /// Decodes the reply to a method call sent with [MethodChannel], treating an empty reply as
+    /// [MethodCallError::NotImplemented].
+    pub fn decode_reply(&self, reply: &[u8]) -> MethodResult {
+        if reply.is_empty() {
+            return Err(MethodCallError::NotImplemented);
+        }
+
+        match self.decode_envelope(reply) {
+            Ok(Ok(value)) => Ok(value.into_owned()),
+            Ok(Err(e)) => Err(MethodCallError::Error(e)),
+            Err(e) => Err(MethodCallError::Decode(e)),
+        }
+    }
+}
+
+impl Default for StandardMethodCodec {
+    fn default() -> Self {
+        StandardMethodCodec::with_extension(&NoExtension)
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
 
 pub trait StandardMethodHandler {
     fn handle(&self, method: &str, args: EncodableValue, reply: StandardMethodReply);
+
+    /// The extension used to read and write custom types on this channel.
+    fn codec_extension(&self) -> &'static dyn CodecExtension {
+        &NoExtension
+    }
+
+    /// Limits applied when decoding incoming method calls.
+    fn decode_limits(&self) -> DecodeLimits {
+        DecodeLimits::default()
+    }
 }
 
 impl<T: StandardMethodHandler> BinaryMessageHandler for T {
     fn handle(&self, message: &[u8], reply: BinaryMessageReply) {
-        let reply = StandardMethodReply(reply);
+        let codec = StandardMethodCodec::with_extension(self.codec_extension())
+            .with_limits(self.decode_limits());
 
-        let mut cursor = Cursor::new(message);
+        let reply = StandardMethodReply { reply, codec };
 
-        let method_name = codec::read_value(&mut cursor).unwrap();
-        let method_args = codec::read_value(&mut cursor).unwrap();
-
-        let EncodableValue::Str(method_name) = method_name else {
-            tracing::error!("invalid method name: {method_name:?}");
-            reply.not_implemented();
-            return;
+        let call = match codec.decode_method_call(message) {
+            Ok(call) => call,
+            Err(e) => {
+                tracing::error!("invalid method call: {e}");
+                reply.error("decode_error", Some(&e.to_string()), &EncodableValue::Null);
+                return;
+            }
         };
 
-        self.handle(method_name, method_args, reply);
+        self.handle(&call.method, call.args, reply);
+    }
+
+    fn panic_reply(&self, message: &str) -> Vec<u8> {
+        StandardMethodCodec::with_extension(self.codec_extension())
+            .encode_error_envelope("panic", Some(message), &EncodableValue::Null)
+            .unwrap_or_default()
     }
 }
 
-pub struct StandardMethodReply(BinaryMessageReply);
+pub struct StandardMethodReply {
+    reply: BinaryMessageReply,
+    codec: StandardMethodCodec,
+}
 
 impl StandardMethodReply {
     pub fn success(self, value: &EncodableValue) {
-        let mut bytes = vec![];
-        let mut cursor = Cursor::new(&mut bytes);
-        cursor.write_all(&[0]).unwrap();
-        codec::write_value(&mut cursor, value).unwrap();
-        self.0.send(&bytes);
+        match self.codec.encode_success_envelope(value) {
+            Ok(bytes) => self.reply.send(&bytes),
+            Err(e) => self.error("encode_error", Some(&e.to_string()), &EncodableValue::Null),
+        }
+    }
+
+    pub fn error(self, code: &str, message: Option<&str>, details: &EncodableValue) {
+        let bytes = match self.codec.encode_error_envelope(code, message, details) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                tracing::error!("failed to encode error details: {e}");
+                self.codec
+                    .encode_error_envelope(code, message, &EncodableValue::Null)
+                    .unwrap_or_else(|e| {
+                        tracing::error!("failed to encode error: {e}");
+                        vec![]
+                    })
+            }
+        };
+        self.reply.send(&bytes);
     }
 
     pub fn not_implemented(self) {
-        self.0.not_implemented();
+        self.reply.not_implemented();
+    }
+}
+
+/// The response to a method call handled by an [AsyncStandardMethodHandler].
+pub enum MethodResponse {
+    Success(EncodableValue<'static>),
+    Error(StandardMethodError),
+    NotImplemented,
+}
+
+impl<E: Into<StandardMethodError>> From<Result<EncodableValue<'static>, E>> for MethodResponse {
+    fn from(res: Result<EncodableValue<'static>, E>) -> Self {
+        match res {
+            Ok(value) => MethodResponse::Success(value),
+            Err(e) => MethodResponse::Error(e.into()),
+        }
+    }
+}
+
+/// A method handler that replies asynchronously. The futures it returns are polled on the
+/// platform thread, so they can await other channel calls or work running on background threads
+/// without blocking the message loop.
+pub trait AsyncStandardMethodHandler: 'static {
+    fn handle(
+        self: Rc<Self>,
+        method: String,
+        args: EncodableValue<'static>,
+    ) -> impl Future<Output = MethodResponse> + 'static;
+
+    /// The extension used to read and write custom types on this channel.
+    fn codec_extension(&self) -> &'static dyn CodecExtension {
+        &NoExtension
+    }
+
+    /// Limits applied when decoding incoming method calls.
+    fn decode_limits(&self) -> DecodeLimits {
+        DecodeLimits::default()
+    }
+}
+
+/// Adapts an [AsyncStandardMethodHandler] so that it can be registered as a platform message
+/// handler.
+pub struct AsyncMethodChannel<T>(Rc<T>);
+
+impl<T: AsyncStandardMethodHandler> AsyncMethodChannel<T> {
+    pub fn new(handler: T) -> AsyncMethodChannel<T> {
+        AsyncMethodChannel(Rc::new(handler))
+    }
+}
+
+impl<T: AsyncStandardMethodHandler> BinaryMessageHandler for AsyncMethodChannel<T> {
+    fn handle(&self, message: &[u8], reply: BinaryMessageReply) {
+        let codec = StandardMethodCodec::with_extension(self.0.codec_extension())
+            .with_limits(self.0.decode_limits());
+
+        let reply = StandardMethodReply { reply, codec };
+
+        let call = match codec.decode_method_call(message) {
+            Ok(call) => call,
+            Err(e) => {
+                tracing::error!("invalid method call: {e}");
+                reply.error("decode_error", Some(&e.to_string()), &EncodableValue::Null);
+                return;
+            }
+        };
+
+        let mut reply = PendingReply(Some(reply));
+        let response = self.0.clone().handle(call.method, call.args.into_owned());
+
+        let res = task_runner::spawn_local(async move {
+            let response = response.await;
+            if let Some(reply) = reply.0.take() {
+                match response {
+                    MethodResponse::Success(value) => reply.success(&value),
+                    MethodResponse::Error(e) => {
+                        reply.error(&e.code, e.message.as_deref(), &e.details)
+                    }
+                    MethodResponse::NotImplemented => reply.not_implemented(),
+                }
+            }
+        });
+
+        // If the future couldn't be spawned, it has already been dropped and replied to.
+        if let Err(e) = res {
+            tracing::error!("failed to spawn method handler: {e}");
+        }
+    }
+
+    fn panic_reply(&self, message: &str) -> Vec<u8> {
+        StandardMethodCodec::with_extension(self.0.codec_extension())
+            .encode_error_envelope("panic", Some(message), &EncodableValue::Null)
+            .unwrap_or_default()
+    }
+}
+
+/// Replies with an error if dropped before the handler's future completes, so that the caller in
+/// Dart doesn't wait forever.
+struct PendingReply(Option<StandardMethodReply>);
+
+impl Drop for PendingReply {
+    fn drop(&mut self) {
+        if let Some(reply) = self.0.take() {
+            reply.error(
+                "cancelled",
+                Some("method call was dropped before completing"),
+                &EncodableValue::Null,
+            );
+        }
+    }
+}
+
+/// Invokes methods on a `MethodChannel` on the Dart side.
+pub struct MethodChannel {
+    messenger: BinaryMessenger,
+    name: CString,
+    codec: StandardMethodCodec,
+}
+
+pub type MethodResult = Result<EncodableValue<'static>, MethodCallError>;
+
+#[derive(Debug)]
+pub enum MethodCallError {
+    /// The method call failed on the Dart side.
+    Error(StandardMethodError),
+    /// There is no handler for the channel on the Dart side, or it doesn't implement the method.
+    NotImplemented,
+    /// The method call couldn't be sent, e.g. because the engine is not running.
+    Send(Error),
+    /// The reply couldn't be decoded.
+    Decode(io::Error),
+}
+
+impl Display for MethodCallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MethodCallError::Error(e) => write!(f, "method call failed: {e}"),
+            MethodCallError::NotImplemented => write!(f, "method not implemented"),
+            MethodCallError::Send(e) => write!(f, "failed to send method call: {e}"),
+            MethodCallError::Decode(e) => write!(f, "failed to decode reply: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for MethodCallError {}
+
+impl MethodChannel {
+    pub fn new(
+        messenger: BinaryMessenger,
+        name: &str,
+        codec: StandardMethodCodec,
+    ) -> crate::Result<MethodChannel> {
+        Ok(MethodChannel {
+            messenger,
+            name: CString::from_str(name).map_err(Error::InvalidChannelName)?,
+            codec,
+        })
+    }
+
+    /// Invokes a method, calling `reply_handler` with the result once Dart replies.
+    pub fn invoke_method_with_reply<F>(
+        &self,
+        method: &str,
+        args: &EncodableValue,
+        reply_handler: F,
+    ) -> crate::Result<()>
+    where
+        F: FnOnce(MethodResult) + 'static,
+    {
+        let message = self
+            .codec
+            .encode_method_call(method, args)
+            .map_err(Error::codec)?;
+        let codec = self.codec;
+        self.messenger
+            .send_platform_message_with_reply(&self.name, &message, move |reply| {
+                reply_handler(codec.decode_reply(reply))
+            })
+    }
+
+    /// Invokes a method, returning a future that resolves once Dart replies.
+    pub fn invoke_method(&self, method: &str, args: &EncodableValue) -> MethodCallFuture {
+        let state = Rc::new(RefCell::new(MethodCallState::Pending(None)));
+
+        let res = self.invoke_method_with_reply(method, args, {
+            let state = state.clone();
+            move |res| state.borrow_mut().complete(res)
+        });
+
+        if let Err(e) = res {
+            state.borrow_mut().complete(Err(MethodCallError::Send(e)));
+        }
+
+        MethodCallFuture(state)
+    }
+}
+
+enum MethodCallState {
+    Pending(Option<Waker>),
+    Complete(MethodResult),
+    Taken,
+}
+
+impl MethodCallState {
+    fn complete(&mut self, res: MethodResult) {
+        let state = std::mem::replace(self, MethodCallState::Complete(res));
+        if let MethodCallState::Pending(Some(waker)) = state {
+            waker.wake();
+        }
+    }
+}
+
+/// The result of [MethodChannel::invoke_method]. This must be polled on the platform thread.
+pub struct MethodCallFuture(Rc<RefCell<MethodCallState>>);
+
+impl Future for MethodCallFuture {
+    type Output = MethodResult;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<MethodResult> {
+        let mut state = self.0.borrow_mut();
+        match std::mem::replace(&mut *state, MethodCallState::Taken) {
+            MethodCallState::Pending(_) => {
+                *state = MethodCallState::Pending(Some(cx.waker().clone()));
+                Poll::Pending
+            }
+            MethodCallState::Complete(res) => Poll::Ready(res),
+            MethodCallState::Taken => panic!("MethodCallFuture polled after completion"),
+        }
     }
 }
@@ -1,7 +1,12 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::future::Future;
 use std::mem;
+use std::pin::Pin;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
 use std::thread::{self, ThreadId};
 use std::time::{Duration, Instant};
 
@@ -25,6 +30,8 @@ use windows::Win32::UI::WindowsAndMessaging::{
 };
 
 use crate::engine::FlutterEngine;
+use crate::error::Error;
+use crate::panic_guard::catch_panic;
 
 #[derive(Debug)]
 pub struct Task(u64, FlutterTask);
@@ -57,23 +64,44 @@ where
 }
 
 pub unsafe extern "C" fn set_thread_priority(thread_priority: i32) {
-    #[expect(non_upper_case_globals)]
-    let priority = match thread_priority {
-        FlutterThreadPriority_kBackground => THREAD_PRIORITY_BELOW_NORMAL,
-        FlutterThreadPriority_kDisplay | FlutterThreadPriority_kRaster => {
-            THREAD_PRIORITY_ABOVE_NORMAL
-        }
-        _ => THREAD_PRIORITY_NORMAL,
-    };
+    catch_panic("thread_priority_setter", || {
+        #[expect(non_upper_case_globals)]
+        let priority = match thread_priority {
+            FlutterThreadPriority_kBackground => THREAD_PRIORITY_BELOW_NORMAL,
+            FlutterThreadPriority_kDisplay | FlutterThreadPriority_kRaster => {
+                THREAD_PRIORITY_ABOVE_NORMAL
+            }
+            _ => THREAD_PRIORITY_NORMAL,
+        };
 
-    if let Err(e) = SetThreadPriority(GetCurrentThread(), priority) {
-        tracing::error!("failed to set thread priority: {e}");
-    }
+        if let Err(e) = SetThreadPriority(GetCurrentThread(), priority) {
+            tracing::error!("failed to set thread priority: {e}");
+        }
+    });
 }
 
 pub struct FlutterTaskExecutor {
     hwnd: HWND,
     queue: Arc<FlutterTaskQueue>,
+    futures: Rc<LocalFutures>,
+}
+
+thread_local! {
+    static LOCAL_SPAWNER: RefCell<Option<LocalSpawner>> = const { RefCell::new(None) };
+}
+
+/// Spawns a future on the executor running on the current thread, which must be the platform
+/// thread. The future is first polled on the next iteration of the message loop.
+pub fn spawn_local(future: impl Future<Output = ()> + 'static) -> crate::Result<()> {
+    LOCAL_SPAWNER.with_borrow(|spawner| match spawner {
+        Some(spawner) => {
+            spawner.spawn(Box::pin(future));
+            Ok(())
+        }
+        None => Err(Error::backend(
+            "no task executor running on the current thread",
+        )),
+    })
 }
 
 impl FlutterTaskExecutor {
@@ -105,9 +133,15 @@ impl FlutterTaskExecutor {
         let queue = Arc::new(FlutterTaskQueue {
             hwnd,
             tasks: Mutex::new(Vec::new()),
+            woken_futures: Mutex::new(Vec::new()),
+            callbacks: Mutex::new(Vec::new()),
         });
 
-        Ok(FlutterTaskExecutor { hwnd, queue })
+        Ok(FlutterTaskExecutor {
+            hwnd,
+            queue,
+            futures: Default::default(),
+        })
     }
 
     pub fn init(&self, engine: Rc<FlutterEngine>) {
@@ -115,11 +149,17 @@ impl FlutterTaskExecutor {
             hwnd: self.hwnd,
             engine,
             queue: self.queue.clone(),
+            futures: self.futures.clone(),
         }));
 
         unsafe {
             SetWindowLongPtrW(self.hwnd, GWLP_USERDATA, state as isize);
         }
+
+        LOCAL_SPAWNER.set(Some(LocalSpawner {
+            queue: self.queue.clone(),
+            futures: self.futures.clone(),
+        }));
     }
 
     pub fn queue(&self) -> &Arc<FlutterTaskQueue> {
@@ -146,6 +186,13 @@ impl FlutterTaskExecutor {
 
 impl Drop for FlutterTaskExecutor {
     fn drop(&mut self) {
+        LOCAL_SPAWNER.set(None);
+
+        // Drop any futures that haven't completed yet, so that they can clean up (e.g. by replying
+        // to pending platform messages) while the engine is still alive.
+        let futures = mem::take(&mut *self.futures.futures.borrow_mut());
+        drop(futures);
+
         unsafe {
             let state =
                 GetWindowLongPtrW(self.hwnd, GWLP_USERDATA) as *mut FlutterTaskExecutorState;
@@ -165,6 +212,7 @@ struct FlutterTaskExecutorState {
     hwnd: HWND,
     queue: Arc<FlutterTaskQueue>,
     engine: Rc<FlutterEngine>,
+    futures: Rc<LocalFutures>,
 }
 
 impl FlutterTaskExecutorState {
@@ -201,6 +249,13 @@ impl FlutterTaskExecutorState {
             }
         }
 
+        let callbacks = mem::take(&mut *self.queue.callbacks.lock());
+        for callback in callbacks {
+            catch_panic("posted_callback", callback);
+        }
+
+        self.poll_futures();
+
         if let Some(time) = next_task_target_time {
             let delta = time - Instant::now();
             unsafe {
@@ -208,11 +263,78 @@ impl FlutterTaskExecutorState {
             }
         }
     }
+
+    fn poll_futures(&mut self) {
+        let mut woken = mem::take(&mut *self.queue.woken_futures.lock());
+        woken.sort_unstable();
+        woken.dedup();
+
+        for id in woken {
+            // The future is removed from the map while it is polled, so that it can spawn other
+            // futures. It may also have already completed, if it was woken more than once.
+            let Some(mut future) = self.futures.futures.borrow_mut().remove(&id) else {
+                continue;
+            };
+
+            let waker = Waker::from(Arc::new(FutureWaker {
+                id,
+                queue: self.queue.clone(),
+            }));
+
+            // A future that panics is dropped, and isn't polled again.
+            let poll = catch_panic("poll_future", || {
+                future.as_mut().poll(&mut Context::from_waker(&waker))
+            });
+
+            if let Some(Poll::Pending) = poll {
+                self.futures.futures.borrow_mut().insert(id, future);
+            }
+        }
+    }
+}
+
+type LocalFuture = Pin<Box<dyn Future<Output = ()>>>;
+
+/// Futures spawned on the platform thread, which are polled by the executor when woken.
+#[derive(Default)]
+struct LocalFutures {
+    next_id: Cell<u64>,
+    futures: RefCell<HashMap<u64, LocalFuture>>,
+}
+
+#[derive(Clone)]
+struct LocalSpawner {
+    queue: Arc<FlutterTaskQueue>,
+    futures: Rc<LocalFutures>,
+}
+
+impl LocalSpawner {
+    fn spawn(&self, future: LocalFuture) {
+        let id = self.futures.next_id.get();
+        self.futures.next_id.set(id + 1);
+        self.futures.futures.borrow_mut().insert(id, future);
+        self.queue.wake_future(id);
+    }
 }
 
+struct FutureWaker {
+    id: u64,
+    queue: Arc<FlutterTaskQueue>,
+}
+
+impl Wake for FutureWaker {
+    fn wake(self: Arc<Self>) {
+        self.queue.wake_future(self.id);
+    }
+}
+
+type PostedCallback = Box<dyn FnOnce() + Send>;
+
 pub struct FlutterTaskQueue {
     hwnd: HWND,
     tasks: Mutex<Vec<Task>>,
+    woken_futures: Mutex<Vec<u64>>,
+    callbacks: Mutex<Vec<PostedCallback>>,
 }
 
 unsafe impl Send for FlutterTaskQueue {}
@@ -225,6 +347,17 @@ impl FlutterTaskQueue {
         self.wake();
     }
 
+    /// Runs `callback` on the platform thread, on the next iteration of the message loop.
+    pub fn post(&self, callback: impl FnOnce() + Send + 'static) {
+        self.callbacks.lock().push(Box::new(callback));
+        self.wake();
+    }
+
+    fn wake_future(&self, id: u64) {
+        self.woken_futures.lock().push(id);
+        self.wake();
+    }
+
     pub fn wake(&self) {
         unsafe {
             if let Err(e) = PostMessageW(
@@ -2,9 +2,9 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use serde::{Deserialize, Serialize};
-use serde_json::json;
 
-use crate::engine::{BinaryMessageHandler, BinaryMessageReply, FlutterEngine};
+use crate::engine::FlutterEngine;
+use crate::json_method_channel::{JsonMethodCodec, JsonMethodHandler, JsonMethodReply};
 use crate::window::{KeyAction, KeyEvent};
 
 pub struct TextInputState {
@@ -39,20 +39,14 @@ impl TextInputState {
                 }
             }
 
-            let message = json!({
-                "method": "TextInputClient.updateEditingState",
-                "args": [
-                    client,
-                    &self.value,
-                ],
-            });
-
-            let message = serde_json::to_vec(&message).unwrap();
+            let message = JsonMethodCodec::encode_method_call(
+                "TextInputClient.updateEditingState",
+                &(client, &self.value),
+            )?;
 
             engine
                 .messenger()
-                .send_platform_message(c"flutter/textinput", &message)
-                .unwrap();
+                .send_platform_message(c"flutter/textinput", &message)?;
         }
 
         Ok(())
@@ -83,21 +77,6 @@ impl TextInputState {
     }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(tag = "method", content = "args")]
-enum TextInputRequest {
-    #[serde(rename = "TextInput.setClient")]
-    SetClient(u32, #[allow(unused)] serde_json::Value),
-    #[serde(rename = "TextInput.clearClient")]
-    ClearClient,
-    #[serde(rename = "TextInput.show")]
-    Show,
-    #[serde(rename = "TextInput.hide")]
-    Hide,
-    #[serde(rename = "TextInput.setEditingState")]
-    SetEditingState(TextEditingValue),
-}
-
 #[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TextEditingValue {
@@ -120,38 +99,41 @@ impl TextInputHandler {
     }
 }
 
-impl BinaryMessageHandler for TextInputHandler {
-    fn handle(&self, message: &[u8], reply: BinaryMessageReply) {
-        let Ok(req) = serde_json::from_slice::<TextInputRequest>(message) else {
-            let message = std::str::from_utf8(message).unwrap();
-            tracing::warn!("unimplemented: {message}");
-            reply.not_implemented();
-            return;
-        };
-
-        tracing::debug!("{req:?}");
-
-        const RES_SUCCESS: &[u8] = c"[null]".to_bytes();
-
-        match req {
-            TextInputRequest::SetClient(client, _) => {
+impl JsonMethodHandler for TextInputHandler {
+    fn handle(&self, method: &str, args: serde_json::Value, reply: JsonMethodReply) {
+        tracing::debug!(method, %args);
+
+        match method {
+            "TextInput.setClient" => {
+                let (client, _) = match serde_json::from_value::<(u32, serde_json::Value)>(args) {
+                    Ok(args) => args,
+                    Err(e) => {
+                        reply.error("invalid_args", Some(&e.to_string()), &());
+                        return;
+                    }
+                };
                 self.state.borrow_mut().client = Some(client);
-                reply.send(RES_SUCCESS);
+                reply.success(&());
             }
-            TextInputRequest::ClearClient => {
+            "TextInput.clearClient" => {
                 self.state.borrow_mut().client = None;
-                reply.send(RES_SUCCESS);
+                reply.success(&());
             }
-            TextInputRequest::Show => {
-                reply.not_implemented();
+            "TextInput.setEditingState" => {
+                let value = match serde_json::from_value::<TextEditingValue>(args) {
+                    Ok(value) => value,
+                    Err(e) => {
+                        reply.error("invalid_args", Some(&e.to_string()), &());
+                        return;
+                    }
+                };
+                self.state.borrow_mut().value = value;
+                reply.success(&());
             }
-            TextInputRequest::Hide => {
+            _ => {
+                tracing::warn!(method, "unimplemented");
                 reply.not_implemented();
             }
-            TextInputRequest::SetEditingState(value) => {
-                self.state.borrow_mut().value = value;
-                reply.send(RES_SUCCESS);
-            }
         }
     }
 }
@@ -0,0 +1,120 @@
+#![cfg(feature = "accesskit")]
+
+use accesskit::{Action, ActionData, ActionRequest, NodeId, Role};
+use flion::basic_message_channel::{MessageCodec, StandardMessageCodec};
+use flion::codec::EncodableValue;
+use flion::semantics::accesskit::{semantics_action, tree_update, SemanticsActionRequest};
+use flion::semantics::{
+    SemanticsActions, SemanticsFlags, SemanticsNode, SemanticsTree, SemanticsUpdate,
+};
+
+fn request(action: Action, data: Option<ActionData>) -> ActionRequest {
+    ActionRequest {
+        action,
+        target: NodeId(5),
+        data,
+    }
+}
+
+fn decode_args(request: &SemanticsActionRequest) -> EncodableValue<'_> {
+    StandardMessageCodec::default()
+        .decode_message(&request.args)
+        .unwrap()
+}
+
+#[test]
+fn simple_actions() {
+    for (action, expected) in [
+        (Action::Click, SemanticsActions::TAP),
+        (Action::Focus, SemanticsActions::FOCUS),
+        (Action::Increment, SemanticsActions::INCREASE),
+        (Action::Decrement, SemanticsActions::DECREASE),
+        (Action::ShowContextMenu, SemanticsActions::LONG_PRESS),
+        (Action::ScrollIntoView, SemanticsActions::SHOW_ON_SCREEN),
+    ] {
+        assert_eq!(
+            semantics_action(&request(action, None)),
+            Some(SemanticsActionRequest {
+                node_id: 5,
+                action: expected,
+                args: vec![],
+            }),
+            "{action:?}"
+        );
+    }
+}
+
+#[test]
+fn scroll_directions_are_inverted() {
+    for (action, expected) in [
+        (Action::ScrollUp, SemanticsActions::SCROLL_DOWN),
+        (Action::ScrollDown, SemanticsActions::SCROLL_UP),
+        (Action::ScrollLeft, SemanticsActions::SCROLL_RIGHT),
+        (Action::ScrollRight, SemanticsActions::SCROLL_LEFT),
+    ] {
+        let result = semantics_action(&request(action, None)).unwrap();
+        assert_eq!(result.action, expected, "{action:?}");
+    }
+}
+
+#[test]
+fn set_value_sends_text() {
+    let data = ActionData::Value("hello".into());
+    let result = semantics_action(&request(Action::SetValue, Some(data))).unwrap();
+
+    assert_eq!(result.action, SemanticsActions::SET_TEXT);
+    assert_eq!(decode_args(&result), EncodableValue::from("hello"));
+
+    assert_eq!(semantics_action(&request(Action::SetValue, None)), None);
+}
+
+#[test]
+fn custom_action_sends_id() {
+    let data = ActionData::CustomAction(7);
+    let result = semantics_action(&request(Action::CustomAction, Some(data))).unwrap();
+
+    assert_eq!(result.action, SemanticsActions::CUSTOM_ACTION);
+    assert_eq!(decode_args(&result), EncodableValue::I32(7));
+}
+
+#[test]
+fn unsupported_action() {
+    assert_eq!(semantics_action(&request(Action::Blur, None)), None);
+}
+
+#[test]
+fn out_of_range_target() {
+    let mut request = request(Action::Click, None);
+    request.target = NodeId(1 << 31);
+    assert_eq!(semantics_action(&request), None);
+}
+
+#[test]
+fn tree_update_includes_root_and_focus() {
+    let empty = tree_update(&SemanticsTree::default());
+    assert_eq!(empty.nodes.len(), 1);
+    assert_eq!(empty.nodes[0].0, NodeId(0));
+    assert_eq!(empty.nodes[0].1.role(), Role::Window);
+    assert_eq!(empty.focus, NodeId(0));
+
+    let mut tree = SemanticsTree::default();
+    tree.apply(SemanticsUpdate {
+        nodes: vec![
+            SemanticsNode {
+                id: 0,
+                children: vec![1],
+                ..Default::default()
+            },
+            SemanticsNode {
+                id: 1,
+                flags: SemanticsFlags::IS_FOCUSED,
+                ..Default::default()
+            },
+        ],
+        custom_actions: vec![],
+    });
+
+    let update = tree_update(&tree);
+    assert_eq!(update.nodes.len(), 2);
+    assert_eq!(update.focus, NodeId(1));
+}
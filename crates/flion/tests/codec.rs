@@ -0,0 +1,207 @@
+use std::borrow::Cow;
+use std::io::Cursor;
+
+use flion::codec::{self, DecodeLimits, Decoder, EncodableValue, Float32, Float64};
+
+/// A small deterministic PRNG, so that failures are reproducible without extra dependencies.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn byte(&mut self) -> u8 {
+        // Bias towards valid type tags and size prefixes, to get past the first byte more often.
+        match self.below(4) {
+            0 => self.below(15) as u8,
+            1 => [0, 1, 2, 253, 254, 255][self.below(6) as usize],
+            _ => self.next() as u8,
+        }
+    }
+}
+
+fn random_value(rng: &mut XorShift, depth: u32) -> EncodableValue<'static> {
+    let max = if depth > 3 { 11 } else { 13 };
+    match rng.below(max) {
+        0 => EncodableValue::Null,
+        1 => EncodableValue::Bool(rng.below(2) == 0),
+        2 => EncodableValue::I32(rng.next() as i32),
+        3 => EncodableValue::I64(rng.next() as i64),
+        4 => EncodableValue::F64(Float64(rng.next() as f64 / 3.0)),
+        5 => EncodableValue::from("x".repeat(rng.below(300) as usize)),
+        6 => EncodableValue::from((0..rng.below(300)).map(|v| v as u8).collect::<Vec<_>>()),
+        7 => EncodableValue::I32List((0..rng.below(20)).map(|v| v as i32).collect()),
+        8 => EncodableValue::I64List((0..rng.below(20)).map(|v| v as i64).collect()),
+        9 => EncodableValue::F32List((0..rng.below(20)).map(|v| Float32(v as f32)).collect()),
+        10 => EncodableValue::F64List((0..rng.below(20)).map(|v| Float64(v as f64)).collect()),
+        11 => EncodableValue::List(
+            (0..rng.below(5))
+                .map(|_| random_value(rng, depth + 1))
+                .collect(),
+        ),
+        _ => EncodableValue::Map(
+            (0..rng.below(5))
+                .map(|_| (random_value(rng, depth + 1), random_value(rng, depth + 1)))
+                .collect(),
+        ),
+    }
+}
+
+fn encode(value: &EncodableValue) -> Vec<u8> {
+    let mut bytes = vec![];
+    codec::write_value(&mut Cursor::new(&mut bytes), value).unwrap();
+    bytes
+}
+
+#[test]
+fn random_bytes_do_not_panic() {
+    let mut rng = XorShift(0x2545f4914f6cdd1d);
+    for _ in 0..50_000 {
+        let len = rng.below(64) as usize;
+        let bytes: Vec<u8> = (0..len).map(|_| rng.byte()).collect();
+        let _ = codec::read_value(&mut Cursor::new(&bytes[..]));
+    }
+}
+
+#[test]
+fn random_values_round_trip() {
+    let mut rng = XorShift(0x9e3779b97f4a7c15);
+    for _ in 0..2_000 {
+        let value = random_value(&mut rng, 0);
+        let bytes = encode(&value);
+        let mut cursor = Cursor::new(&bytes[..]);
+        assert_eq!(codec::read_value(&mut cursor).unwrap(), value);
+        assert_eq!(cursor.position() as usize, bytes.len());
+    }
+}
+
+#[test]
+fn mutated_values_do_not_panic() {
+    let mut rng = XorShift(0xd1b54a32d192ed03);
+    for _ in 0..5_000 {
+        let mut bytes = encode(&random_value(&mut rng, 0));
+        for _ in 0..=rng.below(4) {
+            let i = rng.below(bytes.len() as u64) as usize;
+            bytes[i] = rng.byte();
+        }
+        let _ = codec::read_value(&mut Cursor::new(&bytes[..]));
+    }
+}
+
+#[test]
+fn truncated_values_are_errors() {
+    let mut rng = XorShift(0x94d049bb133111eb);
+    for _ in 0..2_000 {
+        let bytes = encode(&random_value(&mut rng, 0));
+        let len = rng.below(bytes.len() as u64) as usize;
+        assert!(codec::read_value(&mut Cursor::new(&bytes[..len])).is_err());
+    }
+}
+
+#[test]
+fn oversized_string_is_an_error() {
+    // String claiming 0xffffffff bytes, followed by only a few.
+    let bytes = [7, 255, 255, 255, 255, 255, b'a', b'b'];
+    assert!(codec::read_value(&mut Cursor::new(&bytes[..])).is_err());
+}
+
+#[test]
+fn deep_nesting_is_an_error() {
+    // Lists of one element nested far beyond the default depth limit.
+    let bytes: Vec<u8> = [12, 1].repeat(1_000_000);
+    let error = codec::read_value(&mut Cursor::new(&bytes[..])).unwrap_err();
+    assert!(error.to_string().contains("depth"), "{error}");
+}
+
+#[test]
+fn collection_length_limit() {
+    let limits = DecodeLimits {
+        max_collection_len: 3,
+        ..Default::default()
+    };
+
+    let ok = encode(&EncodableValue::List(vec![EncodableValue::Null; 3]));
+    let mut decoder = Decoder::default().with_limits(limits);
+    assert!(decoder.read_value(&mut Cursor::new(&ok[..])).is_ok());
+
+    for value in [
+        EncodableValue::List(vec![EncodableValue::Null; 4]),
+        EncodableValue::from(vec![0u8; 4]),
+        EncodableValue::Map((0..4).map(|i| (i.into(), i.into())).collect()),
+    ] {
+        let bytes = encode(&value);
+        let mut decoder = Decoder::default().with_limits(limits);
+        let error = decoder
+            .read_value(&mut Cursor::new(&bytes[..]))
+            .unwrap_err();
+        assert!(error.to_string().contains("length"), "{error}");
+    }
+}
+
+#[test]
+fn allocation_limit() {
+    let limits = DecodeLimits {
+        max_allocation: 1024,
+        ..Default::default()
+    };
+
+    let bytes = encode(&EncodableValue::List(vec![EncodableValue::Null; 1000]));
+    let mut decoder = Decoder::default().with_limits(limits);
+    let error = decoder
+        .read_value(&mut Cursor::new(&bytes[..]))
+        .unwrap_err();
+    assert!(error.to_string().contains("allocation"), "{error}");
+}
+
+/// Returns whether each of the typed lists in a list borrows from the message.
+fn borrowed(value: &EncodableValue) -> Vec<bool> {
+    let EncodableValue::List(values) = value else {
+        panic!("expected a list: {value:?}");
+    };
+
+    values
+        .iter()
+        .map(|value| match value {
+            EncodableValue::I32List(list) => matches!(list, Cow::Borrowed(_)),
+            EncodableValue::I64List(list) => matches!(list, Cow::Borrowed(_)),
+            EncodableValue::F32List(list) => matches!(list, Cow::Borrowed(_)),
+            EncodableValue::F64List(list) => matches!(list, Cow::Borrowed(_)),
+            value => panic!("expected a typed list: {value:?}"),
+        })
+        .collect()
+}
+
+#[test]
+fn typed_lists_in_aligned_and_misaligned_buffers() {
+    let value = EncodableValue::List(vec![
+        EncodableValue::I32List(vec![1, -2, 3].into()),
+        EncodableValue::I64List(vec![1 << 40, -1].into()),
+        EncodableValue::F32List(vec![Float32(0.5), Float32(-1.0)].into()),
+        EncodableValue::F64List(vec![Float64(0.25), Float64(-8.0)].into()),
+    ]);
+    let bytes = encode(&value);
+
+    let mut buf = vec![0; bytes.len() + 16];
+    let start = buf.as_ptr().align_offset(8);
+
+    // Elements are aligned relative to the start of the message, so they can be borrowed when the
+    // message itself is aligned in memory.
+    buf[start..][..bytes.len()].copy_from_slice(&bytes);
+    let decoded = codec::read_value(&mut Cursor::new(&buf[start..])).unwrap();
+    assert_eq!(decoded, value);
+    assert_eq!(borrowed(&decoded), [true; 4]);
+
+    // Otherwise they are copied out of the buffer.
+    buf[start + 1..][..bytes.len()].copy_from_slice(&bytes);
+    let decoded = codec::read_value(&mut Cursor::new(&buf[start + 1..])).unwrap();
+    assert_eq!(decoded, value);
+    assert_eq!(borrowed(&decoded), [false; 4]);
+}
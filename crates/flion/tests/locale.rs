@@ -0,0 +1,80 @@
+use flion::{resolve_locale, Locale};
+
+#[test]
+fn parse_bcp47_with_script() {
+    assert_eq!(
+        Locale::parse("zh-Hant-TW"),
+        Some(Locale::new("zh").with_script("Hant").with_country("TW"))
+    );
+}
+
+#[test]
+fn parse_posix_name() {
+    assert_eq!(
+        Locale::parse("en_GB.UTF-8"),
+        Some(Locale::new("en").with_country("GB"))
+    );
+
+    // The modifier is not a variant.
+    assert_eq!(
+        Locale::parse("sr_RS@latin"),
+        Some(Locale::new("sr").with_country("RS"))
+    );
+}
+
+#[test]
+fn parse_numeric_region() {
+    assert_eq!(
+        Locale::parse("es-419"),
+        Some(Locale::new("es").with_country("419"))
+    );
+}
+
+#[test]
+fn parse_c_locale() {
+    assert_eq!(Locale::parse("C.UTF-8"), None);
+    assert_eq!(Locale::parse("C"), None);
+    assert_eq!(Locale::parse("POSIX"), None);
+}
+
+#[test]
+fn display() {
+    let locale = Locale::parse("zh_hant_tw").unwrap();
+    assert_eq!(locale.to_string(), "zh-Hant-TW");
+}
+
+#[test]
+fn resolve_prefers_best_match() {
+    let supported = [
+        Locale::new("en").with_country("GB"),
+        Locale::new("en"),
+        Locale::new("en").with_country("US"),
+    ];
+
+    let preferred = [Locale::new("en").with_country("US")];
+    assert_eq!(resolve_locale(&preferred, &supported), Some(2));
+}
+
+#[test]
+fn resolve_tie_prefers_earliest_supported() {
+    let supported = [
+        Locale::new("fr"),
+        Locale::new("en").with_country("GB"),
+        Locale::new("en"),
+    ];
+
+    let preferred = [Locale::new("en").with_country("AU")];
+    assert_eq!(resolve_locale(&preferred, &supported), Some(1));
+}
+
+#[test]
+fn resolve_falls_back_to_later_preferences() {
+    let supported = [Locale::new("fr"), Locale::new("de")];
+
+    let preferred = [Locale::new("ja"), Locale::new("de")];
+    assert_eq!(resolve_locale(&preferred, &supported), Some(1));
+
+    let preferred = [Locale::new("ja")];
+    assert_eq!(resolve_locale(&preferred, &supported), Some(0));
+    assert_eq!(resolve_locale(&preferred, &[]), None);
+}
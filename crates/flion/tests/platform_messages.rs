@@ -0,0 +1,134 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use flion::{BinaryMessageHandler, BinaryMessageReply, PlatformMessageHandlers};
+
+/// Replies with its name, so that tests can check which handler a message was passed to.
+struct Named(&'static str);
+
+impl BinaryMessageHandler for Named {
+    fn handle(&self, _message: &[u8], reply: BinaryMessageReply) {
+        reply.send(self.0.as_bytes());
+    }
+}
+
+/// Dispatches a message, and returns the reply if one was sent before returning.
+fn dispatch(handlers: &PlatformMessageHandlers, channel: &str) -> Option<Vec<u8>> {
+    dispatch_later(handlers, channel).take()
+}
+
+/// Dispatches a message, and returns a cell that holds the reply once it is sent.
+fn dispatch_later(
+    handlers: &PlatformMessageHandlers,
+    channel: &str,
+) -> Rc<RefCell<Option<Vec<u8>>>> {
+    let reply = Rc::new(RefCell::new(None));
+    handlers.dispatch(
+        channel,
+        &[],
+        BinaryMessageReply::from_fn({
+            let reply = reply.clone();
+            move |message| *reply.borrow_mut() = Some(message.to_vec())
+        }),
+    );
+    reply
+}
+
+#[test]
+fn unregistered_channel_is_not_implemented() {
+    let handlers = PlatformMessageHandlers::new();
+    handlers.set("registered", Box::new(Named("registered")));
+
+    // Not implemented replies are passed to the callback as empty.
+    assert_eq!(dispatch(&handlers, "flutter/platform"), Some(vec![]));
+}
+
+#[test]
+fn buffered_until_handler_is_set() {
+    let handlers = PlatformMessageHandlers::new().with_buffer_size(1);
+
+    let first = dispatch_later(&handlers, "channel");
+    let second = dispatch_later(&handlers, "channel");
+
+    // The oldest message is dropped when the buffer is full.
+    assert_eq!(first.take(), Some(vec![]));
+    assert_eq!(second.take(), None);
+
+    handlers.set("channel", Box::new(Named("channel")));
+    assert_eq!(second.take(), Some(b"channel".to_vec()));
+}
+
+#[test]
+fn buffering_can_be_enabled_per_channel() {
+    let handlers = PlatformMessageHandlers::new();
+    handlers.resize_buffer("buffered", 1);
+
+    let buffered = dispatch_later(&handlers, "buffered");
+    assert_eq!(buffered.take(), None);
+    assert_eq!(dispatch(&handlers, "other"), Some(vec![]));
+
+    handlers.set("buffered", Box::new(Named("buffered")));
+    assert_eq!(buffered.take(), Some(b"buffered".to_vec()));
+}
+
+#[test]
+fn exact_channel_takes_precedence_over_prefix() {
+    let handlers = PlatformMessageHandlers::new();
+    handlers.set("plugin/*", Box::new(Named("prefix")));
+    handlers.set("plugin/channel", Box::new(Named("exact")));
+
+    assert_eq!(
+        dispatch(&handlers, "plugin/channel"),
+        Some(b"exact".to_vec())
+    );
+    assert_eq!(
+        dispatch(&handlers, "plugin/other"),
+        Some(b"prefix".to_vec())
+    );
+}
+
+#[test]
+fn longest_prefix_wins() {
+    let handlers = PlatformMessageHandlers::new();
+    handlers.set("plugin/*", Box::new(Named("short")));
+    handlers.set("plugin/player/*", Box::new(Named("long")));
+
+    assert_eq!(
+        dispatch(&handlers, "plugin/player/1"),
+        Some(b"long".to_vec())
+    );
+    assert_eq!(
+        dispatch(&handlers, "plugin/recorder/1"),
+        Some(b"short".to_vec())
+    );
+}
+
+#[test]
+fn sibling_prefixes_do_not_match() {
+    let handlers = PlatformMessageHandlers::new();
+    handlers.set("a/b*", Box::new(Named("b")));
+    handlers.set("a/bc*", Box::new(Named("bc")));
+
+    assert_eq!(dispatch(&handlers, "a/b"), Some(b"b".to_vec()));
+    assert_eq!(dispatch(&handlers, "a/bd"), Some(b"b".to_vec()));
+    assert_eq!(dispatch(&handlers, "a/bc"), Some(b"bc".to_vec()));
+    assert_eq!(dispatch(&handlers, "a/bcd"), Some(b"bc".to_vec()));
+    assert_eq!(dispatch(&handlers, "a/c"), Some(vec![]));
+}
+
+#[test]
+fn removed_prefix_falls_back_to_shorter_prefix() {
+    let handlers = PlatformMessageHandlers::new();
+    handlers.set("plugin/*", Box::new(Named("short")));
+    handlers.set("plugin/player/*", Box::new(Named("long")));
+
+    assert!(handlers.remove("plugin/player/*").is_some());
+    assert_eq!(
+        dispatch(&handlers, "plugin/player/1"),
+        Some(b"short".to_vec())
+    );
+
+    assert!(handlers.remove("plugin/*").is_some());
+    assert!(handlers.remove("plugin/*").is_none());
+    assert_eq!(dispatch(&handlers, "plugin/player/1"), Some(vec![]));
+}
@@ -0,0 +1,81 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use flion::replay::{MessageRecording, RecordedMessage};
+use flion::{BinaryMessageHandler, BinaryMessageReply, PlatformMessageHandlers};
+
+/// Replies with the message reversed.
+struct Reverse;
+
+impl BinaryMessageHandler for Reverse {
+    fn handle(&self, message: &[u8], reply: BinaryMessageReply) {
+        let mut message = message.to_vec();
+        message.reverse();
+        reply.send(&message);
+    }
+}
+
+fn message(channel: &str, message: &[u8], reply: Option<&[u8]>) -> RecordedMessage {
+    RecordedMessage {
+        channel: channel.to_owned(),
+        message: message.to_vec(),
+        reply: reply.map(<[u8]>::to_vec),
+    }
+}
+
+fn handlers() -> PlatformMessageHandlers {
+    let handlers = PlatformMessageHandlers::new();
+    handlers.set("reverse", Box::new(Reverse));
+    handlers
+}
+
+#[test]
+fn matching_replies() {
+    let recording = MessageRecording {
+        messages: vec![
+            message("reverse", &[1, 2, 3], Some(&[3, 2, 1])),
+            // Not implemented replies are recorded as empty.
+            message("missing", &[1], Some(&[])),
+            // Messages without a recorded reply are skipped.
+            message("reverse", &[1, 2], None),
+        ],
+    };
+
+    recording.replay(&handlers(), &[]).unwrap();
+}
+
+#[test]
+fn mismatched_reply() {
+    let recording = MessageRecording {
+        messages: vec![
+            message("reverse", &[1, 2], Some(&[2, 1])),
+            message("reverse", &[1, 2], Some(&[1, 2])),
+        ],
+    };
+
+    let mismatch = recording.replay(&handlers(), &[]).unwrap_err();
+    assert_eq!(mismatch.index, 1);
+    assert_eq!(mismatch.channel, "reverse");
+    assert_eq!(mismatch.expected, [1, 2]);
+    assert_eq!(mismatch.actual, Some(vec![2, 1]));
+
+    recording.replay(&handlers(), &["reverse"]).unwrap();
+}
+
+#[test]
+fn save_and_load() {
+    let recording = MessageRecording {
+        messages: vec![
+            message("reverse", &[1, 2, 3], Some(&[3, 2, 1])),
+            message("pending", &[], None),
+        ],
+    };
+
+    let path = env::temp_dir().join(format!("flion-recording-{}.json", process::id()));
+    recording.save(&path).unwrap();
+    let loaded = MessageRecording::load(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.unwrap(), recording);
+}
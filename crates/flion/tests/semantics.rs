@@ -0,0 +1,135 @@
+use flion::semantics::{
+    CustomSemanticsAction, SemanticsActions, SemanticsFlags, SemanticsNode, SemanticsTree,
+    SemanticsUpdate,
+};
+
+fn node(id: i32, children: &[i32]) -> SemanticsNode {
+    SemanticsNode {
+        id,
+        children: children.to_vec(),
+        ..Default::default()
+    }
+}
+
+fn update(nodes: Vec<SemanticsNode>) -> SemanticsUpdate {
+    SemanticsUpdate {
+        nodes,
+        custom_actions: vec![],
+    }
+}
+
+fn sorted(mut ids: Vec<i32>) -> Vec<i32> {
+    ids.sort();
+    ids
+}
+
+fn tree() -> SemanticsTree {
+    let mut tree = SemanticsTree::default();
+    let removed = tree.apply(update(vec![
+        node(0, &[1, 2]),
+        node(1, &[3]),
+        node(2, &[]),
+        node(3, &[]),
+    ]));
+
+    assert!(removed.is_empty());
+    tree
+}
+
+#[test]
+fn apply_adds_nodes() {
+    let tree = tree();
+
+    assert_eq!(tree.root().map(|node| &node.children), Some(&vec![1, 2]));
+    assert_eq!(
+        sorted(tree.nodes().map(|node| node.id).collect()),
+        [0, 1, 2, 3]
+    );
+}
+
+#[test]
+fn apply_keeps_unchanged_nodes() {
+    let mut tree = tree();
+
+    let mut changed = node(2, &[]);
+    changed.label = "changed".to_owned();
+
+    assert!(tree.apply(update(vec![changed])).is_empty());
+    assert_eq!(
+        tree.node(2).map(|node| node.label.as_str()),
+        Some("changed")
+    );
+    assert!(tree.node(3).is_some());
+}
+
+#[test]
+fn apply_removes_unreachable_subtrees() {
+    let mut tree = tree();
+
+    let removed = tree.apply(update(vec![node(0, &[2])]));
+
+    assert_eq!(sorted(removed), [1, 3]);
+    assert!(tree.node(1).is_none());
+    assert!(tree.node(3).is_none());
+    assert!(tree.node(2).is_some());
+}
+
+#[test]
+fn apply_removes_nodes_without_a_parent() {
+    let mut tree = tree();
+
+    // A node that was never attached to the tree is dropped straight away.
+    let removed = tree.apply(update(vec![node(4, &[])]));
+
+    assert_eq!(removed, [4]);
+    assert!(tree.node(4).is_none());
+}
+
+#[test]
+fn apply_without_root_removes_everything() {
+    let mut tree = SemanticsTree::default();
+
+    let removed = tree.apply(update(vec![node(1, &[2]), node(2, &[])]));
+
+    assert_eq!(sorted(removed), [1, 2]);
+    assert!(tree.root().is_none());
+}
+
+#[test]
+fn focused_node() {
+    let mut tree = tree();
+    assert!(tree.focused().is_none());
+
+    let mut focused = node(3, &[]);
+    focused.flags = SemanticsFlags::IS_FOCUSABLE | SemanticsFlags::IS_FOCUSED;
+    tree.apply(update(vec![focused]));
+
+    assert_eq!(tree.focused().map(|node| node.id), Some(3));
+
+    // The focused node is removed along with its parent.
+    tree.apply(update(vec![node(0, &[2])]));
+    assert!(tree.focused().is_none());
+}
+
+#[test]
+fn custom_actions() {
+    let mut tree = tree();
+
+    tree.apply(SemanticsUpdate {
+        nodes: vec![],
+        custom_actions: vec![CustomSemanticsAction {
+            id: 7,
+            override_action: Some(SemanticsActions::TAP),
+            label: "activate".to_owned(),
+            hint: String::new(),
+        }],
+    });
+
+    let action = tree.custom_action(7).unwrap();
+    assert_eq!(action.label, "activate");
+    assert_eq!(action.override_action, Some(SemanticsActions::TAP));
+
+    tree.clear();
+    assert!(tree.custom_action(7).is_none());
+    assert!(tree.root().is_none());
+}
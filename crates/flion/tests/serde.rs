@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use flion::codec::{self, EncodableValue};
+use serde::{Deserialize, Serialize, Serializer};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Shape {
+    Empty,
+    Circle(f64),
+    Rect { width: f64, height: f64 },
+    Line(i32, i32),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Item<'a> {
+    id: i64,
+    name: &'a str,
+    label: Option<String>,
+    shape: Shape,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Document<'a> {
+    #[serde(borrow)]
+    items: Vec<Item<'a>>,
+    attributes: BTreeMap<String, i32>,
+}
+
+/// Bytes that are serialized as a `Uint8List` rather than a list of integers.
+#[derive(Debug, PartialEq, Serialize)]
+struct Bytes<'a>(#[serde(serialize_with = "as_bytes")] &'a [u8]);
+
+fn as_bytes<S: Serializer>(value: &&[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bytes(value)
+}
+
+fn round_trip<'a, T>(value: &T, bytes: &'a mut Vec<u8>) -> T
+where
+    T: Debug + Serialize + Deserialize<'a>,
+{
+    *bytes = codec::to_vec(value).unwrap();
+    codec::from_slice(bytes).unwrap()
+}
+
+fn document() -> Document<'static> {
+    Document {
+        items: vec![
+            Item {
+                id: 1 << 40,
+                name: "first",
+                label: Some("label".to_owned()),
+                shape: Shape::Rect {
+                    width: 1.5,
+                    height: 2.0,
+                },
+            },
+            Item {
+                id: -1,
+                name: "second",
+                label: None,
+                shape: Shape::Line(3, 4),
+            },
+        ],
+        attributes: BTreeMap::from([("a".to_owned(), 1), ("b".to_owned(), 2)]),
+    }
+}
+
+#[test]
+fn struct_round_trip() {
+    let value = document();
+    let mut bytes = vec![];
+    assert_eq!(round_trip(&value, &mut bytes), value);
+}
+
+#[test]
+fn enum_round_trip() {
+    for value in [
+        Shape::Empty,
+        Shape::Circle(0.5),
+        Shape::Rect {
+            width: 1.0,
+            height: 2.0,
+        },
+        Shape::Line(-1, 1),
+    ] {
+        let mut bytes = vec![];
+        assert_eq!(round_trip(&value, &mut bytes), value);
+    }
+}
+
+#[test]
+fn option_round_trip() {
+    for value in [None, Some(42i32)] {
+        let mut bytes = vec![];
+        assert_eq!(round_trip(&value, &mut bytes), value);
+    }
+
+    assert_eq!(codec::to_value(&None::<i32>).unwrap(), EncodableValue::Null);
+}
+
+#[test]
+fn map_round_trip() {
+    let value = BTreeMap::from([(1, "one".to_owned()), (2, "two".to_owned())]);
+    let mut bytes = vec![];
+    assert_eq!(round_trip(&value, &mut bytes), value);
+}
+
+#[test]
+fn bytes_are_a_u8_list() {
+    assert_eq!(
+        codec::to_value(&Bytes(&[0, 1, 2, 255])).unwrap(),
+        EncodableValue::U8List(vec![0, 1, 2, 255].into())
+    );
+}
+
+#[test]
+fn bytes_borrow_from_the_message() {
+    let bytes = codec::to_vec(&Bytes(&[0, 1, 2, 255])).unwrap();
+    let data: &[u8] = codec::from_slice(&bytes).unwrap();
+    assert_eq!(data, [0, 1, 2, 255]);
+}
+
+#[test]
+fn error_path_for_nested_mismatch() {
+    let value = serde_json::json!({
+        "items": [{"id": "1", "name": "first", "label": null, "shape": "Empty"}],
+    });
+
+    let bytes = codec::to_vec(&value).unwrap();
+    let error = codec::from_slice::<BTreeMap<String, Vec<Item>>>(&bytes).unwrap_err();
+    assert_eq!(error.path(), "items[0].id", "{error}");
+}
+
+#[test]
+fn trailing_bytes_are_an_error() {
+    let mut bytes = codec::to_vec(&document()).unwrap();
+    assert!(codec::from_slice::<Document>(&bytes).is_ok());
+
+    bytes.extend([1, 2, 3]);
+    let error = codec::from_slice::<Document>(&bytes).unwrap_err();
+    assert!(error.to_string().contains("trailing"), "{error}");
+}
@@ -0,0 +1,107 @@
+use flion::codec::{EncodableValue, Float32, Float64};
+use flion::standard_method_channel::{MethodCallError, StandardMethodCodec};
+
+/// Values that are aligned relative to the start of the message when encoded.
+fn aligned_values() -> Vec<EncodableValue<'static>> {
+    vec![
+        EncodableValue::F64(Float64(1.5)),
+        EncodableValue::F32List(vec![Float32(1.0), Float32(-2.5)].into()),
+        EncodableValue::I64List(vec![1 << 40, -1].into()),
+        EncodableValue::F64List(vec![Float64(0.25), Float64(-8.0)].into()),
+        EncodableValue::List(vec![
+            EncodableValue::from("a"),
+            EncodableValue::F64(Float64(2.0)),
+            EncodableValue::I32List(vec![1, 2, 3].into()),
+        ]),
+    ]
+}
+
+#[test]
+fn success_envelope_round_trip() {
+    let codec = StandardMethodCodec::default();
+    for value in aligned_values() {
+        let bytes = codec.encode_success_envelope(&value).unwrap();
+        let decoded = codec.decode_envelope(&bytes).unwrap();
+        assert_eq!(decoded.unwrap(), value);
+    }
+}
+
+#[test]
+fn error_envelope_round_trip() {
+    let codec = StandardMethodCodec::default();
+    for details in aligned_values() {
+        let bytes = codec
+            .encode_error_envelope("code", Some("message"), &details)
+            .unwrap();
+        let error = codec.decode_envelope(&bytes).unwrap().unwrap_err();
+        assert_eq!(error.code, "code");
+        assert_eq!(error.message.as_deref(), Some("message"));
+        assert_eq!(error.details, details);
+    }
+
+    let bytes = codec
+        .encode_error_envelope("code", None, &EncodableValue::Null)
+        .unwrap();
+    let error = codec.decode_envelope(&bytes).unwrap().unwrap_err();
+    assert_eq!(error.message, None);
+}
+
+#[test]
+fn invalid_envelopes_are_errors() {
+    let codec = StandardMethodCodec::default();
+    assert!(codec.decode_envelope(&[]).is_err());
+    assert!(codec.decode_envelope(&[2, 0]).is_err());
+    // An error envelope whose code isn't a string.
+    assert!(codec.decode_envelope(&[1, 3, 1, 0, 0, 0, 0, 0]).is_err());
+}
+
+#[test]
+fn method_call_round_trip() {
+    let codec = StandardMethodCodec::default();
+    for args in aligned_values() {
+        let bytes = codec.encode_method_call("method", &args).unwrap();
+        let call = codec.decode_method_call(&bytes).unwrap();
+        assert_eq!(call.method, "method");
+        assert_eq!(call.args, args);
+    }
+}
+
+#[test]
+fn reply_success() {
+    let codec = StandardMethodCodec::default();
+    for value in aligned_values() {
+        let bytes = codec.encode_success_envelope(&value).unwrap();
+        assert_eq!(codec.decode_reply(&bytes).unwrap(), value);
+    }
+}
+
+#[test]
+fn reply_error() {
+    let codec = StandardMethodCodec::default();
+    let details = EncodableValue::F64List(vec![Float64(1.0)].into());
+    let bytes = codec
+        .encode_error_envelope("code", Some("message"), &details)
+        .unwrap();
+
+    match codec.decode_reply(&bytes) {
+        Err(MethodCallError::Error(e)) => {
+            assert_eq!(e.code, "code");
+            assert_eq!(e.message.as_deref(), Some("message"));
+            assert_eq!(e.details, details);
+        }
+        res => panic!("unexpected reply: {res:?}"),
+    }
+}
+
+#[test]
+fn empty_reply_is_not_implemented() {
+    let codec = StandardMethodCodec::default();
+    assert!(matches!(
+        codec.decode_reply(&[]),
+        Err(MethodCallError::NotImplemented)
+    ));
+    assert!(matches!(
+        codec.decode_reply(&[2]),
+        Err(MethodCallError::Decode(_))
+    ));
+}
@@ -44,7 +44,7 @@ impl SolidColorView {
         context: CompositorContext,
         _id: i32,
         _args: EncodableValue,
-    ) -> color_eyre::Result<Box<dyn PlatformView>> {
+    ) -> flion::Result<Box<dyn PlatformView>> {
         let visual = unsafe { context.composition_device.CreateVisual()? };
 
         // Create a 1x1 bitmap surface. This will be scaled to fill the size of the platform
@@ -100,7 +100,7 @@ impl PlatformView for SolidColorView {
         &self.visual
     }
 
-    fn update(&mut self, args: &flion::PlatformViewUpdateArgs) -> color_eyre::eyre::Result<()> {
+    fn update(&mut self, args: &flion::PlatformViewUpdateArgs) -> flion::Result<()> {
         unsafe {
             self.visual.SetTransform2(&Matrix3x2 {
                 M11: args.width as f32,